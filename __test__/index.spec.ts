@@ -1,8 +1,41 @@
 import test from 'ava'
 import rsZip from '../index.js'
-const { zip, unzip } = rsZip
-import { join } from 'path'
-import { existsSync, mkdirSync, writeFileSync, readFileSync, rmSync, chmodSync, statSync } from 'fs'
+import type { Progress } from '../index.js'
+const {
+  zip,
+  zipToBuffer,
+  zipStream,
+  unzip,
+  unzipBuffer,
+  unzipStream,
+  readAllFromBuffer,
+  list,
+  readEntry,
+  mergeArchives,
+  removeEntries,
+  renameEntries,
+  test: testArchive,
+} = rsZip
+import { basename, join } from 'path'
+import { once } from 'events'
+import { spawnSync } from 'child_process'
+import {
+  existsSync,
+  mkdirSync,
+  writeFileSync,
+  readFileSync,
+  rmSync,
+  chmodSync,
+  statSync,
+  lstatSync,
+  readlinkSync,
+  readdirSync,
+  symlinkSync,
+  utimesSync,
+  createReadStream,
+} from 'fs'
+import { Readable } from 'stream'
+import { randomBytes } from 'crypto'
 
 const TEST_DIR = join(process.cwd(), 'temp_test_dir')
 const SRC_DIR = join(TEST_DIR, 'src')
@@ -61,6 +94,179 @@ test('zip with exclude', async (t) => {
   t.true(existsSync(join(outDir, 'file1.txt')), 'Regular file should exist')
 })
 
+test('zip prunes excluded directories', async (t) => {
+  const srcDir = join(TEST_DIR, 'prune_src')
+  mkdirSync(join(srcDir, 'node_modules', 'dep'), { recursive: true })
+  mkdirSync(join(srcDir, 'lib', 'node_modules'), { recursive: true })
+  writeFileSync(join(srcDir, 'node_modules', 'dep', 'index.js'), 'dep')
+  writeFileSync(join(srcDir, 'lib', 'node_modules', 'nested.js'), 'nested')
+  writeFileSync(join(srcDir, 'lib', 'index.js'), 'lib')
+
+  const outZip = join(TEST_DIR, 'prune.zip')
+  await zip(srcDir, outZip, { exclude: ['**/node_modules/**'] })
+  t.deepEqual(
+    (await list(outZip)).map((e) => e.name),
+    ['lib/', 'lib/index.js'],
+  )
+
+  const archive = await zipToBuffer(
+    [
+      { name: 'dist/', content: '' },
+      { name: 'dist/a.js', content: 'A' },
+      { name: 'b.js', content: 'B' },
+    ],
+    { exclude: ['dist'] },
+  )
+  t.deepEqual(Object.keys(await readAllFromBuffer(archive)), ['b.js'])
+
+  // A negated glob can still re-include files inside a directory whose contents are excluded
+  mkdirSync(join(srcDir, 'dist'))
+  writeFileSync(join(srcDir, 'dist', 'keep.txt'), 'keep')
+  writeFileSync(join(srcDir, 'dist', 'other.txt'), 'other')
+  const negatedZip = join(TEST_DIR, 'prune_negated.zip')
+  await zip(srcDir, negatedZip, { exclude: ['**/node_modules/**', 'dist/**', '!dist/keep.txt'] })
+  t.deepEqual((await list(negatedZip)).map((e) => e.name).sort(), ['dist/', 'dist/keep.txt', 'lib/', 'lib/index.js'])
+
+  const negated = await zipToBuffer(
+    [
+      { name: 'dist/keep.txt', content: 'K' },
+      { name: 'dist/other.txt', content: 'O' },
+    ],
+    { exclude: ['dist/**', '!dist/keep.txt'] },
+  )
+  t.deepEqual(Object.keys(await readAllFromBuffer(negated)), ['dist/keep.txt'])
+})
+
+test('zip with include, negation, ignore files and junk presets', async (t) => {
+  const srcDir = join(TEST_DIR, 'filter_src')
+  mkdirSync(join(srcDir, 'sub', 'build'), { recursive: true })
+  mkdirSync(join(srcDir, '.git'), { recursive: true })
+  writeFileSync(join(srcDir, '.gitignore'), '*.log\n!keep.log\nbuild/\n/root.txt\n')
+  writeFileSync(join(srcDir, 'sub', '.gitignore'), 'secret.txt\n!app.log\n')
+  writeFileSync(join(srcDir, '.zipignore'), '*.map\n')
+  for (const name of ['app.log', 'keep.log', 'root.txt', 'a.js', 'a.js.map', '.DS_Store', '.git/HEAD']) {
+    writeFileSync(join(srcDir, name), name)
+  }
+  for (const name of ['root.txt', 'secret.txt', 'app.log', 'Thumbs.db', 'build/out.js']) {
+    writeFileSync(join(srcDir, 'sub', name), name)
+  }
+
+  const names = async (options: Parameters<typeof zipToBuffer>[1]) => {
+    const outZip = join(TEST_DIR, 'filter_names.zip')
+    writeFileSync(outZip, await zipToBuffer(srcDir, options))
+    return (await list(outZip)).map((e) => e.name).sort()
+  }
+
+  t.deepEqual(await names({ respectGitignore: true, ignoreFiles: ['.zipignore'], excludeJunk: true }), [
+    '.gitignore',
+    '.zipignore',
+    'a.js',
+    'keep.log',
+    'sub/',
+    'sub/.gitignore',
+    'sub/app.log',
+    'sub/root.txt',
+  ])
+  t.deepEqual(await names({ include: ['**/*.js', '!sub/**'] }), ['a.js'])
+  t.deepEqual(await names({ include: ['*.log'], exclude: ['*.log', '!keep.log'] }), ['keep.log'])
+
+  const archive = await zipToBuffer(
+    [
+      { name: '__MACOSX/', content: '' },
+      { name: '__MACOSX/._a.txt', content: 'junk' },
+      { name: 'a.txt', content: 'A' },
+    ],
+    { excludeJunk: true },
+  )
+  t.deepEqual(Object.keys(await readAllFromBuffer(archive)), ['a.txt'])
+})
+
+test('zip maps multiple sources to archive names', async (t) => {
+  const outZip = join(TEST_DIR, 'sources.zip')
+  const count = await zip(
+    [
+      { src: join(SRC_DIR, 'subdir'), dest: 'app/' },
+      { src: join(SRC_DIR, 'file1.txt') },
+      { src: join(SRC_DIR, 'file2.txt'), dest: 'docs/README.txt' },
+      { src: join(SRC_DIR, '*.sh'), dest: 'bin' },
+    ],
+    outZip,
+  )
+  t.is(count, 4)
+  t.deepEqual((await list(outZip)).map((e) => e.name).sort(), [
+    'app/file3.txt',
+    'bin/script.sh',
+    'docs/README.txt',
+    'file1.txt',
+  ])
+
+  await t.throwsAsync(
+    zip([{ src: join(SRC_DIR, 'file1.txt') }, { src: join(SRC_DIR, 'file2.txt'), dest: 'file1.txt' }], outZip),
+    { message: /both map to 'file1.txt'/ },
+  )
+  await t.throwsAsync(zip([{ src: join(SRC_DIR, 'missing.txt') }], outZip), { message: /Failed to read source/ })
+  await t.throwsAsync(
+    async () => {
+      await zip([{ src: join(SRC_DIR, 'file1.txt'), dest: '../../evil.txt' }], outZip)
+    },
+    { message: /Invalid dest/ },
+  )
+
+  // Directories matched by a glob are walked
+  await zip([{ src: join(SRC_DIR, 'sub*'), dest: 'lib' }], outZip)
+  t.deepEqual((await list(outZip)).map((e) => e.name).sort(), ['lib/subdir/', 'lib/subdir/file3.txt'])
+})
+
+test('zip puts entries under a prefix', async (t) => {
+  const outZip = join(TEST_DIR, 'prefix.zip')
+  await zip(SRC_DIR, outZip, { prefix: 'myapp-1.2.3/', exclude: ['subdir', '*.tmp'], overrides: { '*.txt': 'store' } })
+  const entries = await list(outZip)
+  t.deepEqual(entries.map((e) => e.name).sort(), [
+    'myapp-1.2.3/',
+    'myapp-1.2.3/file1.txt',
+    'myapp-1.2.3/file2.txt',
+    'myapp-1.2.3/script.sh',
+  ])
+  t.is(entries.find((e) => e.name === 'myapp-1.2.3/file1.txt')!.method, 'store')
+
+  await t.throwsAsync(
+    async () => {
+      await zip(SRC_DIR, outZip, { prefix: '../escape' })
+    },
+    { message: /Invalid prefix/ },
+  )
+})
+
+test('zip updates, freshens and appends to an existing archive', async (t) => {
+  const srcDir = join(TEST_DIR, 'update_src')
+  mkdirSync(srcDir, { recursive: true })
+  const past = new Date(Date.now() - 60_000)
+  for (const name of ['a.txt', 'b.txt']) {
+    writeFileSync(join(srcDir, name), name)
+    utimesSync(join(srcDir, name), past, past)
+  }
+  const outZip = join(TEST_DIR, 'update.zip')
+  await zip(srcDir, outZip)
+  t.is(await zip(srcDir, outZip, { mode: 'update' }), 0)
+
+  writeFileSync(join(srcDir, 'a.txt'), 'changed')
+  writeFileSync(join(srcDir, 'c.txt'), 'new')
+  t.is(await zip(srcDir, outZip, { mode: 'freshen' }), 1)
+  t.deepEqual((await list(outZip)).map((e) => e.name).sort(), ['a.txt', 'b.txt'])
+  t.is(await zip(srcDir, outZip, { mode: 'update' }), 1)
+  t.deepEqual((await list(outZip)).map((e) => e.name).sort(), ['a.txt', 'b.txt', 'c.txt'])
+  t.is((await readEntry(outZip, 'a.txt')).toString(), 'changed')
+
+  t.is(await zip(srcDir, outZip, { mode: 'append', prefix: 'copy' }), 3)
+  t.is(await zip(srcDir, outZip, { mode: 'append', prefix: 'copy' }), 3)
+  t.is((await list(outZip)).length, 7)
+  t.deepEqual((await testArchive(outZip)).errors, [])
+
+  await t.throwsAsync(zip(srcDir, join(TEST_DIR, 'missing.zip'), { mode: 'freshen' }), {
+    message: /Failed to open zip file/,
+  })
+})
+
 test('zip preserves permissions (Unix)', async (t) => {
   if (process.platform === 'win32') {
     t.pass('Skipping permission test on Windows')
@@ -79,6 +285,143 @@ test('zip preserves permissions (Unix)', async (t) => {
   t.true(isExecutable, 'Should preserve executable permission')
 })
 
+test('zip preserves, follows or skips symlinks (Unix)', async (t) => {
+  if (process.platform === 'win32') {
+    t.pass('Skipping symlink test on Windows')
+    return
+  }
+
+  const srcDir = join(TEST_DIR, 'links_src')
+  mkdirSync(join(srcDir, 'pkg'), { recursive: true })
+  mkdirSync(join(srcDir, 'node_modules'))
+  writeFileSync(join(srcDir, 'pkg', 'index.js'), 'module.exports = 1')
+  symlinkSync('../pkg', join(srcDir, 'node_modules', 'pkg'))
+  symlinkSync('/etc/hosts', join(srcDir, 'hosts'))
+
+  const preserved = await zipToBuffer(srcDir, { symlinks: 'preserve' })
+  const outDir = join(TEST_DIR, 'out_links')
+  await unzipBuffer(preserved, outDir)
+  t.true(lstatSync(join(outDir, 'node_modules', 'pkg')).isSymbolicLink())
+  t.is(readlinkSync(join(outDir, 'node_modules', 'pkg')), '../pkg')
+  t.is(readFileSync(join(outDir, 'node_modules', 'pkg', 'index.js'), 'utf8'), 'module.exports = 1')
+  t.false(existsSync(join(outDir, 'hosts')), 'Should skip links pointing outside the output directory')
+
+  const followed = await readAllFromBuffer(await zipToBuffer(srcDir))
+  t.is(followed['node_modules/pkg/index.js'].toString(), 'module.exports = 1')
+
+  const skipped = await readAllFromBuffer(await zipToBuffer(srcDir, { symlinks: 'skip' }))
+  t.deepEqual(Object.keys(skipped), ['pkg/index.js'])
+})
+
+test('unzip skips links climbing out through other links (Unix)', async (t) => {
+  if (process.platform === 'win32') {
+    t.pass('Skipping symlink test on Windows')
+    return
+  }
+
+  // Each target stays inside on its own, but `t` climbs out through `s/up`
+  const srcDir = join(TEST_DIR, 'chained_links_src')
+  mkdirSync(join(srcDir, 's'), { recursive: true })
+  symlinkSync('..', join(srcDir, 's', 'up'))
+  symlinkSync('s/up/..', join(srcDir, 't'))
+  const sorted = join(TEST_DIR, 'chained_links.zip')
+  await zip(srcDir, sorted, { symlinks: 'preserve', deterministic: true })
+  // Same links with `t` first, before `s/up` exists
+  const reversed = join(TEST_DIR, 'chained_links_reversed.zip')
+  await zip([{ src: join(srcDir, 't') }, { src: join(srcDir, 's'), dest: 's/' }], reversed, { symlinks: 'preserve' })
+
+  for (const archive of [sorted, reversed]) {
+    for (const [method, extract] of [
+      ['unzip', (outDir: string) => unzip(archive, outDir)],
+      ['unzipBuffer', (outDir: string) => unzipBuffer(readFileSync(archive), outDir)],
+      ['unzipStream', (outDir: string) => unzipStream(createReadStream(archive), outDir)],
+    ] as const) {
+      const outDir = join(TEST_DIR, `out_${basename(archive, '.zip')}_${method}`, 'inner')
+      await extract(outDir)
+      t.is(readlinkSync(join(outDir, 's', 'up')), '..')
+      t.deepEqual(readdirSync(outDir), ['s'], `${method} should skip links climbing out of ${archive}`)
+    }
+  }
+})
+
+test('unzipStream restores symlinks (Unix)', async (t) => {
+  if (process.platform === 'win32') {
+    t.pass('Skipping symlink test on Windows')
+    return
+  }
+
+  const srcDir = join(TEST_DIR, 'stream_links_src')
+  mkdirSync(srcDir, { recursive: true })
+  writeFileSync(join(srcDir, 'target.txt'), 'Linked')
+  symlinkSync('target.txt', join(srcDir, 'link.txt'))
+
+  const outDir = join(TEST_DIR, 'out_stream_links')
+  await unzipStream(zipStream(srcDir, { symlinks: 'preserve' }), outDir)
+  t.is(readlinkSync(join(outDir, 'link.txt')), 'target.txt')
+  t.is(readFileSync(join(outDir, 'link.txt'), 'utf8'), 'Linked')
+})
+
+test('zip with deterministic builds identical archives', async (t) => {
+  const first = join(TEST_DIR, 'deterministic_a')
+  const second = join(TEST_DIR, 'deterministic_b')
+  mkdirSync(join(first, 'nested'), { recursive: true })
+  writeFileSync(join(first, 'b.txt'), 'B')
+  writeFileSync(join(first, 'nested', 'a.txt'), 'A')
+  // Same content, created in another order with other permissions
+  mkdirSync(second)
+  writeFileSync(join(second, 'b.txt'), 'B', { mode: 0o600 })
+  mkdirSync(join(second, 'nested'))
+  writeFileSync(join(second, 'nested', 'a.txt'), 'A')
+
+  const archive = await zipToBuffer(first, { deterministic: true })
+  t.deepEqual(await zipToBuffer(second, { deterministic: true }), archive)
+
+  const outZip = join(TEST_DIR, 'deterministic.zip')
+  writeFileSync(outZip, archive)
+  const entries = await list(outZip)
+  t.deepEqual(
+    entries.map((e) => e.name),
+    ['b.txt', 'nested/', 'nested/a.txt'],
+  )
+  t.true(entries.every((e) => e.mtime === Date.UTC(1980, 0, 1)))
+  t.is(entries[0].unixMode! & 0o777, 0o644)
+})
+
+test('zip rejects deterministic with a password', async (t) => {
+  await t.throwsAsync(
+    async () => {
+      await zipToBuffer(SRC_DIR, { deterministic: true, password: 'secret' })
+    },
+    { message: /can't be encrypted/ },
+  )
+})
+
+test('zip and unzip preserve modification times', async (t) => {
+  const srcDir = join(TEST_DIR, 'mtime_src')
+  mkdirSync(join(srcDir, 'nested'), { recursive: true })
+  writeFileSync(join(srcDir, 'nested', 'old.txt'), 'Old')
+  // Odd seconds, which the DOS timestamp alone can't hold
+  const fileTime = new Date(Date.UTC(2015, 2, 4, 5, 6, 7))
+  const dirTime = new Date(Date.UTC(2016, 0, 1, 0, 0, 1))
+  utimesSync(join(srcDir, 'nested', 'old.txt'), fileTime, fileTime)
+  utimesSync(join(srcDir, 'nested'), dirTime, dirTime)
+
+  const outZip = join(TEST_DIR, 'mtime.zip')
+  await zip(srcDir, outZip)
+  const outDir = join(TEST_DIR, 'out_mtime')
+  await unzip(outZip, outDir)
+  t.is(statSync(join(outDir, 'nested', 'old.txt')).mtimeMs, fileTime.getTime())
+  t.is(statSync(join(outDir, 'nested')).mtimeMs, dirTime.getTime())
+
+  const streamDir = join(TEST_DIR, 'out_mtime_stream')
+  await unzipStream(createReadStream(outZip), streamDir)
+  t.is(statSync(join(streamDir, 'nested', 'old.txt')).mtimeMs, fileTime.getTime())
+
+  const freshDir = join(TEST_DIR, 'out_mtime_fresh')
+  await unzip(outZip, freshDir, { restoreMtimes: false })
+  t.true(statSync(join(freshDir, 'nested', 'old.txt')).mtimeMs > fileTime.getTime())
+})
+
 test('zip rejects invalid level', async (t) => {
   await t.throwsAsync(
     async () => {
@@ -88,3 +431,526 @@ test('zip rejects invalid level', async (t) => {
     { message: /between 0 and 9/ },
   )
 })
+
+test('zip and unzip report progress', async (t) => {
+  const outZip = join(TEST_DIR, 'progress.zip')
+  const outDir = join(TEST_DIR, 'out_progress')
+
+  const zipUpdates: Progress[] = []
+  const count = await zip(SRC_DIR, outZip, { onProgress: (p) => zipUpdates.push(p) })
+  t.true(zipUpdates.length > 0, 'Should report zip progress')
+  const lastZip = zipUpdates[zipUpdates.length - 1]
+  // Directory entries are counted as well
+  t.true(lastZip.entriesProcessed >= count)
+  t.true(lastZip.bytesProcessed > 0)
+
+  const unzipUpdates: Progress[] = []
+  await unzip(outZip, outDir, { onProgress: (p) => unzipUpdates.push(p) })
+  const lastUnzip = unzipUpdates[unzipUpdates.length - 1]
+  t.is(lastUnzip.entriesProcessed, lastUnzip.entriesTotal)
+  t.is(lastUnzip.bytesProcessed, lastUnzip.bytesTotal)
+})
+
+test('zip and unzip reject with AbortError when aborted', async (t) => {
+  const outZip = join(TEST_DIR, 'abort.zip')
+  const outDir = join(TEST_DIR, 'out_abort')
+
+  const controller = new AbortController()
+  controller.abort()
+
+  await t.throwsAsync(zip(SRC_DIR, outZip, { signal: controller.signal }), {
+    code: 'AbortError',
+    message: 'AbortError',
+  })
+  t.false(existsSync(outZip), 'Partial zip file should be removed')
+  await t.throwsAsync(zipToBuffer(SRC_DIR, { signal: controller.signal }), {
+    code: 'AbortError',
+    message: 'AbortError',
+  })
+
+  await zip(SRC_DIR, outZip)
+  const existing = readFileSync(outZip)
+  await t.throwsAsync(zip(SRC_DIR, outZip, { signal: controller.signal }), { code: 'AbortError' })
+  t.deepEqual(readFileSync(outZip), existing, 'Existing zip file should be kept')
+
+  await t.throwsAsync(unzip(outZip, outDir, { signal: controller.signal }), {
+    code: 'AbortError',
+    message: 'AbortError',
+  })
+  t.false(existsSync(outDir), 'Extracted files should be removed')
+})
+
+test('zip with threads', async (t) => {
+  const outZip = join(TEST_DIR, 'threads.zip')
+  const outDir = join(TEST_DIR, 'out_threads')
+
+  const count = await zip(SRC_DIR, outZip, { threads: 4, exclude: ['*.tmp'] })
+  t.is(count, 4, 'Should compress 4 files (excluding .tmp)')
+
+  await unzip(outZip, outDir)
+  t.is(readFileSync(join(outDir, 'subdir', 'file3.txt'), 'utf8'), 'Nested File')
+  t.false(existsSync(join(outDir, 'ignore.tmp')), 'Ignored file should not exist')
+
+  // Any thread count gives the same bytes as sequential compression
+  const [one, two, eight] = await Promise.all(
+    [1, 2, 8].map((threads) => zipToBuffer(SRC_DIR, { deterministic: true, threads })),
+  )
+  const sequential = await zipToBuffer(SRC_DIR, { deterministic: true })
+  t.deepEqual(sequential, one)
+  t.deepEqual(two, one)
+  t.deepEqual(eight, one)
+})
+
+test('zip rejects zero threads', async (t) => {
+  await t.throwsAsync(
+    async () => {
+      await zip(SRC_DIR, INVALID_ZIP, { threads: 0 })
+    },
+    { message: /at least 1/ },
+  )
+})
+
+test('unzip with threads', async (t) => {
+  const outZip = join(TEST_DIR, 'unzip_threads.zip')
+  const outDir = join(TEST_DIR, 'out_unzip_threads')
+
+  await zip(SRC_DIR, outZip)
+  await unzip(outZip, outDir, { threads: 3 })
+
+  t.is(readFileSync(join(outDir, 'file1.txt'), 'utf8'), 'Hello World')
+  t.is(readFileSync(join(outDir, 'subdir', 'file3.txt'), 'utf8'), 'Nested File')
+  if (process.platform !== 'win32') {
+    t.true((statSync(join(outDir, 'script.sh')).mode & 0o111) !== 0, 'Should preserve executable permission')
+  }
+})
+
+test('zip with each compression method', async (t) => {
+  for (const method of ['store', 'deflate', 'bzip2', 'zstd', 'xz'] as const) {
+    const outZip = join(TEST_DIR, `method_${method}.zip`)
+    const outDir = join(TEST_DIR, `out_method_${method}`)
+
+    const count = await zip(SRC_DIR, outZip, { method })
+    t.is(count, 5, `Should compress 5 files with ${method}`)
+
+    await unzip(outZip, outDir)
+    t.is(readFileSync(join(outDir, 'subdir', 'file3.txt'), 'utf8'), 'Nested File')
+  }
+})
+
+test('zip with level 0 stores entries', async (t) => {
+  const outZip = join(TEST_DIR, 'level0.zip')
+  const outDir = join(TEST_DIR, 'out_level0')
+
+  await zip(SRC_DIR, outZip, { level: 0 })
+  await unzip(outZip, outDir)
+  t.is(readFileSync(join(outDir, 'file1.txt'), 'utf8'), 'Hello World')
+})
+
+test('zip rejects level outside the method range', async (t) => {
+  await t.throwsAsync(
+    async () => {
+      await zip(SRC_DIR, INVALID_ZIP, { method: 'zstd', level: 30 })
+    },
+    { message: /zstd must be between 1 and 22/ },
+  )
+  await t.throwsAsync(
+    async () => {
+      await zip(SRC_DIR, INVALID_ZIP, { method: 'store', level: 42 })
+    },
+    { message: /store must be between 0 and 9/ },
+  )
+})
+
+test('zip with auto store and overrides', async (t) => {
+  const srcDir = join(TEST_DIR, 'src_store')
+  const outDir = join(TEST_DIR, 'out_store')
+  mkdirSync(srcDir, { recursive: true })
+  writeFileSync(join(srcDir, 'image.png'), 'not really a png')
+  writeFileSync(join(srcDir, 'app.log'), 'log line\n'.repeat(100))
+  writeFileSync(join(srcDir, 'notes.txt'), 'notes')
+  writeFileSync(join(srcDir, 'random.bin'), randomBytes(8192))
+
+  const overrides = { '*.log': { level: 9 }, '*.txt': 'store' as const }
+  const cases = [
+    {
+      options: {},
+      methods: { 'app.log': 'deflate', 'image.png': 'store', 'notes.txt': 'deflate', 'random.bin': 'deflate' },
+    },
+    {
+      options: { autoStore: false },
+      methods: { 'app.log': 'deflate', 'image.png': 'deflate', 'notes.txt': 'deflate', 'random.bin': 'deflate' },
+    },
+    {
+      options: { detectIncompressible: true, overrides },
+      methods: { 'app.log': 'deflate', 'image.png': 'store', 'notes.txt': 'store', 'random.bin': 'store' },
+    },
+  ]
+  for (const { options, methods } of cases) {
+    const outZip = join(TEST_DIR, 'store.zip')
+    t.is(await zip(srcDir, outZip, options), 4)
+    // Sorted, as directory walks list files in filesystem order
+    const entries = (await list(outZip)).map((e) => [e.name, e.method]).sort()
+    t.deepEqual(Object.fromEntries(entries), methods)
+
+    await unzip(outZip, outDir)
+    t.is(readFileSync(join(outDir, 'image.png'), 'utf8'), 'not really a png')
+    t.is(readFileSync(join(outDir, 'app.log'), 'utf8'), 'log line\n'.repeat(100))
+  }
+})
+
+test('zip rejects invalid overrides', async (t) => {
+  await t.throwsAsync(
+    async () => {
+      await zip(SRC_DIR, INVALID_ZIP, { overrides: { '*.log': { method: 'bzip2', level: 0 } } })
+    },
+    { message: /bzip2 must be between 1 and 9/ },
+  )
+})
+
+test('zip and unzip with a password', async (t) => {
+  for (const encryption of ['aes128', 'aes192', 'aes256', 'zipcrypto'] as const) {
+    const outZip = join(TEST_DIR, `encrypted_${encryption}.zip`)
+    const outDir = join(TEST_DIR, `out_encrypted_${encryption}`)
+
+    t.is(await zip(SRC_DIR, outZip, { password: 'secret', encryption, threads: 2 }), 5)
+
+    await unzip(outZip, outDir, { password: 'secret' })
+    t.is(readFileSync(join(outDir, 'subdir', 'file3.txt'), 'utf8'), 'Nested File')
+  }
+})
+
+test('unzip rejects wrong or missing passwords', async (t) => {
+  const outZip = join(TEST_DIR, 'encrypted.zip')
+  await zip(SRC_DIR, outZip, { password: 'secret' })
+
+  await t.throwsAsync(
+    async () => {
+      await unzip(outZip, join(TEST_DIR, 'out_wrong_password'), { password: 'wrong' })
+    },
+    { code: 'InvalidPassword', message: /Wrong password/ },
+  )
+  await t.throwsAsync(
+    async () => {
+      await unzip(outZip, join(TEST_DIR, 'out_no_password'))
+    },
+    { code: 'PasswordRequired' },
+  )
+})
+
+test('zip rejects encryption without a password', async (t) => {
+  await t.throwsAsync(
+    async () => {
+      await zip(SRC_DIR, INVALID_ZIP, { encryption: 'aes256' })
+    },
+    { message: /requires a password/ },
+  )
+})
+
+test('list archive entries', async (t) => {
+  const outZip = join(TEST_DIR, 'list.zip')
+  await zip(SRC_DIR, outZip, { method: 'store' })
+
+  const entries = await list(outZip)
+  t.is(entries.filter((e) => !e.isDir).length, 5)
+
+  const file1 = entries.find((e) => e.name === 'file1.txt')
+  t.truthy(file1)
+  t.is(file1?.size, 11)
+  t.is(file1?.compressedSize, 11)
+  t.is(file1?.method, 'store')
+  t.false(file1?.encrypted)
+  t.true(Math.abs((file1?.mtime ?? 0) - Date.now()) < 60_000, 'Should record the current time')
+
+  const subdir = entries.find((e) => e.name === 'subdir/')
+  t.true(subdir?.isDir)
+
+  if (process.platform !== 'win32') {
+    const script = entries.find((e) => e.name === 'script.sh')
+    t.is((script?.unixMode ?? 0) & 0o777, 0o755)
+  }
+})
+
+test('list rejects missing archives', async (t) => {
+  await t.throwsAsync(
+    async () => {
+      await list(join(TEST_DIR, 'missing.zip'))
+    },
+    { message: /Failed to open zip file/ },
+  )
+})
+
+test('readEntry reads a single entry', async (t) => {
+  const outZip = join(TEST_DIR, 'read_entry.zip')
+  await zip(SRC_DIR, outZip, { password: 'secret' })
+
+  const content = await readEntry(outZip, 'subdir/file3.txt', { password: 'secret' })
+  t.is(content.toString('utf8'), 'Nested File')
+
+  await t.throwsAsync(
+    async () => {
+      await readEntry(outZip, 'missing.txt')
+    },
+    { code: 'EntryNotFound' },
+  )
+})
+
+test('removeEntries and renameEntries edit an archive in place', async (t) => {
+  const outZip = join(TEST_DIR, 'edit.zip')
+  await zip(SRC_DIR, outZip, { overrides: { '*.sh': 'store' } })
+
+  t.is(await removeEntries(outZip, ['*.tmp', 'subdir']), 3)
+  t.is(await removeEntries(outZip, ['missing']), 0)
+  t.is(await renameEntries(outZip, { 'file1.txt': 'docs/hello.txt', 'file2.txt': 'file1.txt' }), 2)
+
+  const entries = await list(outZip)
+  t.deepEqual(entries.map((e) => e.name).sort(), ['docs/hello.txt', 'file1.txt', 'script.sh'])
+  t.is(entries.find((e) => e.name === 'script.sh')!.method, 'store')
+  t.is((await readEntry(outZip, 'docs/hello.txt')).toString(), 'Hello World')
+  t.is((await readEntry(outZip, 'file1.txt')).toString(), 'Rust Zip')
+
+  await t.throwsAsync(renameEntries(outZip, { 'missing.txt': 'a.txt' }), { code: 'EntryNotFound' })
+  await t.throwsAsync(renameEntries(outZip, { 'script.sh': 'file1.txt' }), { message: /would both be named/ })
+  await t.throwsAsync(
+    async () => {
+      await removeEntries(outZip, ['[abc'])
+    },
+    { message: /Invalid remove pattern/ },
+  )
+})
+
+test('mergeArchives combines archives without recompression', async (t) => {
+  const first = join(TEST_DIR, 'merge_first.zip')
+  const second = join(TEST_DIR, 'merge_second.zip')
+  writeFileSync(
+    first,
+    await zipToBuffer([
+      { name: 'lib/', content: '' },
+      { name: 'lib/a.js', content: 'A' },
+      { name: 'shared.txt', content: 'first' },
+    ]),
+  )
+  writeFileSync(
+    second,
+    await zipToBuffer(
+      [
+        { name: 'lib/', content: '' },
+        { name: 'lib/b.js', content: 'B' },
+        { name: 'shared.txt', content: 'second' },
+      ],
+      { method: 'store' },
+    ),
+  )
+  const outZip = join(TEST_DIR, 'merged.zip')
+
+  await t.throwsAsync(mergeArchives([first, second], outZip), { message: /Entry 'shared.txt' is in both/ })
+  t.false(existsSync(outZip))
+  writeFileSync(outZip, 'previous')
+  await t.throwsAsync(mergeArchives([first, second], outZip), { message: /Entry 'shared.txt' is in both/ })
+  await t.throwsAsync(mergeArchives([first, join(TEST_DIR, 'merge_missing.zip')], outZip), {
+    message: /Failed to open zip file/,
+  })
+  t.is(readFileSync(outZip, 'utf8'), 'previous')
+
+  t.is(await mergeArchives([first, second], outZip, { onConflict: 'last' }), 4)
+  const entries = await list(outZip)
+  t.deepEqual(entries.map((e) => e.name), ['lib/', 'lib/a.js', 'shared.txt', 'lib/b.js'])
+  t.is(entries.find((e) => e.name === 'shared.txt')!.method, 'store')
+  t.is((await readEntry(outZip, 'shared.txt')).toString(), 'second')
+
+  t.is(await mergeArchives([first, second], outZip, { prefixEach: ['a', 'b'] }), 6)
+  t.is((await readEntry(outZip, 'a/shared.txt')).toString(), 'first')
+  t.deepEqual((await testArchive(outZip)).errors, [])
+})
+
+test('unzip with include and exclude', async (t) => {
+  const outZip = join(TEST_DIR, 'filter.zip')
+  await zip(SRC_DIR, outZip)
+
+  const includeDir = join(TEST_DIR, 'out_include')
+  await unzip(outZip, includeDir, { include: ['subdir/**'] })
+  t.true(existsSync(join(includeDir, 'subdir', 'file3.txt')))
+  t.false(existsSync(join(includeDir, 'file1.txt')))
+
+  const excludeDir = join(TEST_DIR, 'out_exclude')
+  await unzip(outZip, excludeDir, { exclude: ['*.tmp', 'subdir', 'subdir/**'] })
+  t.true(existsSync(join(excludeDir, 'file1.txt')))
+  t.false(existsSync(join(excludeDir, 'ignore.tmp')))
+  t.false(existsSync(join(excludeDir, 'subdir')))
+})
+
+test('globs honor matchOptions and reject syntax errors', async (t) => {
+  const outZip = join(TEST_DIR, 'match_options.zip')
+  await zip(SRC_DIR, outZip, {
+    include: ['*.TXT'],
+    matchOptions: { caseSensitive: false, requireLiteralSeparator: true },
+  })
+  t.deepEqual((await list(outZip)).map((e) => e.name).sort(), ['file1.txt', 'file2.txt'])
+
+  await t.throwsAsync(
+    async () => {
+      await zip(SRC_DIR, outZip, { exclude: ['*.tmp', '![abc'] })
+    },
+    { message: "Invalid exclude pattern '![abc' at position 1: invalid range pattern" },
+  )
+  await t.throwsAsync(
+    async () => {
+      await unzip(outZip, join(TEST_DIR, 'out_invalid_glob'), { include: ['a/**b'] })
+    },
+    { message: /^Invalid include pattern 'a\/\*\*b' at position 4/ },
+  )
+})
+
+test('test reports corrupted entries', async (t) => {
+  const outZip = join(TEST_DIR, 'integrity.zip')
+  await zip(SRC_DIR, outZip, { method: 'store' })
+
+  const report = await testArchive(outZip)
+  t.is(report.entriesTested, 5)
+  t.deepEqual(report.errors, [])
+
+  // Flip a byte of the stored content of file1.txt
+  const corruptZip = join(TEST_DIR, 'integrity_corrupt.zip')
+  const data = readFileSync(outZip)
+  data[data.indexOf('Hello World')] ^= 1
+  writeFileSync(corruptZip, data)
+
+  const corruptReport = await testArchive(corruptZip)
+  t.is(corruptReport.entriesTested, 5)
+  t.is(corruptReport.errors.length, 1)
+  t.is(corruptReport.errors[0].name, 'file1.txt')
+  t.regex(corruptReport.errors[0].message, /checksum/)
+})
+
+test('zipToBuffer from a directory', async (t) => {
+  const outZip = join(TEST_DIR, 'to_buffer.zip')
+  const outDir = join(TEST_DIR, 'out_to_buffer')
+
+  const buffer = await zipToBuffer(SRC_DIR, { exclude: ['*.tmp'] })
+  t.true(Buffer.isBuffer(buffer))
+  writeFileSync(outZip, buffer)
+
+  await unzip(outZip, outDir)
+  t.is(readFileSync(join(outDir, 'subdir', 'file3.txt'), 'utf8'), 'Nested File')
+  t.false(existsSync(join(outDir, 'ignore.tmp')))
+})
+
+test('zipToBuffer from virtual entries', async (t) => {
+  const outZip = join(TEST_DIR, 'virtual.zip')
+  const mtime = Date.UTC(2020, 0, 2, 3, 4, 6)
+
+  const buffer = await zipToBuffer([
+    { name: 'hello.txt', content: 'Hello' },
+    { name: 'bin/', content: '' },
+    { name: 'bin/run', content: Buffer.from('#!/bin/sh'), mode: 0o755, mtime },
+  ])
+  writeFileSync(outZip, buffer)
+
+  const entries = await list(outZip)
+  t.deepEqual(entries.map((e) => e.name), ['hello.txt', 'bin/', 'bin/run'])
+  const run = entries.find((e) => e.name === 'bin/run')
+  t.is((run?.unixMode ?? 0) & 0o777, 0o755)
+  t.is(run?.mtime, mtime)
+  t.is((await readEntry(outZip, 'hello.txt')).toString('utf8'), 'Hello')
+})
+
+test('zipToBuffer rejects out of range mtimes', async (t) => {
+  await t.throwsAsync(
+    async () => {
+      await zipToBuffer([{ name: 'old.txt', content: '', mtime: 0 }])
+    },
+    { message: /1980-2107/ },
+  )
+})
+
+test('zipToBuffer rejects unsafe names', async (t) => {
+  for (const name of ['../evil.txt', 'a/../../evil.txt', '/etc/evil.txt', 'c:/evil.txt']) {
+    await t.throwsAsync(
+      async () => {
+        await zipToBuffer([{ name, content: 'outside' }])
+      },
+      { message: /Invalid virtual entry name/ },
+    )
+  }
+})
+
+test('unzipBuffer extracts to disk', async (t) => {
+  const outDir = join(TEST_DIR, 'out_unzip_buffer')
+  const buffer = await zipToBuffer(SRC_DIR)
+
+  await unzipBuffer(buffer, outDir, { threads: 2 })
+  t.is(readFileSync(join(outDir, 'subdir', 'file3.txt'), 'utf8'), 'Nested File')
+  if (process.platform !== 'win32') {
+    t.true((statSync(join(outDir, 'script.sh')).mode & 0o111) !== 0, 'Should preserve executable permission')
+  }
+})
+
+test('readAllFromBuffer reads every file', async (t) => {
+  // zipToBuffer refuses unsafe names, so the raw name bytes are swapped for one of the same length
+  const safe = await zipToBuffer([
+    { name: 'a.txt', content: 'A' },
+    { name: 'dir/', content: '' },
+    { name: 'dir/b.txt', content: 'B' },
+    { name: 'xx/evil.txt', content: 'outside' },
+  ])
+  const buffer = Buffer.from(safe.toString('latin1').replaceAll('xx/evil.txt', '../evil.txt'), 'latin1')
+
+  const files = await readAllFromBuffer(buffer)
+  t.deepEqual(Object.keys(files), ['a.txt', 'dir/b.txt'])
+  t.is(files['dir/b.txt'].toString('utf8'), 'B')
+})
+
+test('zipStream streams a readable archive', async (t) => {
+  const chunks: Buffer[] = []
+  for await (const chunk of zipStream(SRC_DIR, { exclude: ['*.tmp'], password: 'secret' })) {
+    chunks.push(chunk)
+  }
+
+  const files = await readAllFromBuffer(Buffer.concat(chunks), { password: 'secret' })
+  t.deepEqual(Object.keys(files).sort(), ['file1.txt', 'file2.txt', 'script.sh', 'subdir/file3.txt'])
+  t.is(files['subdir/file3.txt'].toString('utf8'), 'Nested File')
+})
+
+test('zipStream is destroyed when aborted', async (t) => {
+  const controller = new AbortController()
+  controller.abort()
+
+  const stream = zipStream(SRC_DIR, { signal: controller.signal })
+  stream.resume()
+  await t.throwsAsync(once(stream, 'end'), { code: 'AbortError', message: 'AbortError' })
+  t.true(stream.destroyed)
+})
+
+test('zipStream does not keep the process alive when never read', (t) => {
+  const script = `require(${JSON.stringify(join(process.cwd(), 'index.js'))}).zipStream(${JSON.stringify(SRC_DIR)})`
+  const result = spawnSync(process.execPath, ['-e', script], { timeout: 10000 })
+  t.is(result.signal, null, 'Process should exit on its own')
+  t.is(result.status, 0)
+})
+
+test('unzipStream extracts a streamed archive', async (t) => {
+  const outDir = join(TEST_DIR, 'out_unzip_stream')
+
+  await unzipStream(zipStream(SRC_DIR), outDir)
+  t.is(readFileSync(join(outDir, 'subdir', 'file3.txt'), 'utf8'), 'Nested File')
+  if (process.platform !== 'win32') {
+    t.true((statSync(join(outDir, 'script.sh')).mode & 0o111) !== 0, 'Should preserve executable permission')
+  }
+})
+
+test('unzipStream reads a zip file stream with filters', async (t) => {
+  const outZip = join(TEST_DIR, 'stream_source.zip')
+  const outDir = join(TEST_DIR, 'out_unzip_stream_filtered')
+  await zip(SRC_DIR, outZip)
+
+  await unzipStream(createReadStream(outZip, { highWaterMark: 16 }), outDir, { include: ['subdir/**'] })
+  t.is(readFileSync(join(outDir, 'subdir', 'file3.txt'), 'utf8'), 'Nested File')
+  t.false(existsSync(join(outDir, 'file1.txt')))
+})
+
+test('unzipStream rejects truncated input', async (t) => {
+  const buffer = await zipToBuffer(SRC_DIR)
+
+  await t.throwsAsync(unzipStream(Readable.from([buffer.subarray(0, 40)]), join(TEST_DIR, 'out_truncated')), {
+    message: /end of zip stream/,
+  })
+})
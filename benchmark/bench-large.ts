@@ -1,12 +1,14 @@
 import { zip } from '../index.js'
 import { join } from 'path'
 import { existsSync, mkdirSync, writeFileSync, rmSync } from 'fs'
+import { availableParallelism } from 'os'
 import { performance } from 'perf_hooks'
 
 const BENCH_DIR = join(process.cwd(), 'temp_bench_dir')
 const SRC_DIR = join(BENCH_DIR, 'src')
 const OUT_ZIP = join(BENCH_DIR, 'bench.zip')
 const FILE_COUNT = 1000
+const THREADS = availableParallelism()
 
 function setup() {
   if (existsSync(BENCH_DIR)) {
@@ -16,7 +18,11 @@ function setup() {
 
   console.log(`Generating ${FILE_COUNT} files...`)
   for (let i = 0; i < FILE_COUNT; i++) {
-    writeFileSync(join(SRC_DIR, `file_${i}.txt`), `Content for file ${i}.This is some random text to compress.`)
+    // ~256KB of compressible text per file, so compression dominates the run time
+    writeFileSync(
+      join(SRC_DIR, `file_${i}.txt`),
+      `Content for file ${i}. This is some random text to compress. ${Math.random()}\n`.repeat(3000),
+    )
   }
 }
 
@@ -26,19 +32,25 @@ function teardown() {
   }
 }
 
+async function measure(label: string, threads?: number) {
+  const start = performance.now()
+
+  const count = await zip(SRC_DIR, OUT_ZIP, { level: 6, threads })
+
+  const duration = performance.now() - start
+  console.log(`[${label}] Compressed ${count} files in ${duration.toFixed(2)} ms`)
+  return duration
+}
+
 async function run() {
   try {
     setup()
 
     console.log('Starting compression...')
-    const start = performance.now()
-
-    const count = await zip(SRC_DIR, OUT_ZIP)
-
-    const end = performance.now()
-    const duration = end - start
+    const sequential = await measure('sequential')
+    const parallel = await measure(`threads: ${THREADS}`, THREADS)
 
-    console.log(`Compressed ${count} files in ${duration.toFixed(2)} ms`)
+    console.log(`Speedup: ${(sequential / parallel).toFixed(2)}x`)
   } catch (e) {
     console.error('Benchmark failed:', e)
   } finally {
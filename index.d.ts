@@ -1,37 +1,439 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
+/**
+ * Encryption used for file entries when a `password` is set.
+ *
+ * The AES modes are only available when the `aes-crypto` cargo feature is enabled.
+ * `zipcrypto` is weak and only meant for tools that can't read AES archives.
+ */
+export type Encryption = 'aes128' | 'aes192' | 'aes256' | 'zipcrypto'
+
+/** An entry that failed the integrity test. */
+export interface EntryError {
+  name: string
+  message: string
+}
+
+/** Metadata of an archive entry, as read from the central directory. */
+export interface EntryInfo {
+  /** Entry name, directories end with `/` */
+  name: string
+  /** Uncompressed size in bytes */
+  size: number
+  /** Compressed size in bytes */
+  compressedSize: number
+  /** Compression method, e.g. `deflate`, or `unknown (<code>)` */
+  method: string
+  crc32: number
+  /**
+   * Last modification time in milliseconds since the Unix epoch, from the extended
+   * timestamp field when present, else the DOS timestamp read as UTC
+   */
+  mtime?: number
+  /** Unix mode including the file type bits, when the archive records one */
+  unixMode?: number
+  isDir: boolean
+  isSymlink: boolean
+  encrypted: boolean
+  comment: string
+}
+
+/** Compression settings for entries matching an `overrides` glob. */
+export interface EntryOverride {
+  method?: Method
+  level?: number
+}
+
+/**
+ * List the entries of a zip file without extracting it.
+ *
+ * Returns one `EntryInfo` per entry, in archive order.
+ *
+ * # Arguments
+ * * `source_path` - Source zip file path
+ */
+export declare function list(sourcePath: string): Promise<Array<EntryInfo>>
+
+/** How `include`, `exclude` and `overrides` globs match names. */
+export interface MatchOptions {
+  /** Match letters case-sensitively (default: true) */
+  caseSensitive?: boolean
+  /** Only match `/` with a literal `/`, so `*` stays within a directory (default: false) */
+  requireLiteralSeparator?: boolean
+  /** Only match a leading `.` of a file name with a literal `.` (default: false) */
+  requireLiteralLeadingDot?: boolean
+}
+
+/**
+ * Merge zip files into a new one, copying their entries without recompression.
+ *
+ * Returns the number of entries in the merged archive.
+ *
+ * # Arguments
+ * * `inputs` - Zip file paths, in the order their entries are written
+ * * `output_path` - Output zip file path
+ * * `options` - Merge options
+ *   - `onConflict`: What to do with a file several inputs have (default: error)
+ *   - `prefixEach`: Put the entries of each input under its file name without extension (`true`), or under the given directories
+ */
+export declare function mergeArchives(
+  inputs: Array<string>,
+  outputPath: string,
+  options?: MergeOptions | undefined | null,
+): Promise<number>
+
+export interface MergeOptions {
+  onConflict?: OnConflict
+  /**
+   * `true` puts the entries of each input under its file name without extension,
+   * an array gives the directory of each input
+   */
+  prefixEach?: boolean | Array<string>
+}
+
+/**
+ * Compression method used for new entries.
+ *
+ * `bzip2`, `zstd` and `xz` are only available when the matching cargo feature is enabled.
+ */
+export type Method = 'store' | 'deflate' | 'bzip2' | 'zstd' | 'xz'
+
+/**
+ * What `mergeArchives()` does with a file entry several inputs have.
+ *
+ * `error` rejects, `first` keeps the entry of the earliest input and `last`
+ * the one of the latest input.
+ */
+export type OnConflict = 'error' | 'first' | 'last'
+
+/** Progress snapshot passed to the `onProgress` callback. */
+export interface Progress {
+  /** Number of entries processed so far */
+  entriesProcessed: number
+  /** Total number of entries, when known upfront */
+  entriesTotal?: number
+  /** Number of uncompressed bytes read (zip) or written (unzip) so far */
+  bytesProcessed: number
+  /** Total number of uncompressed bytes, when known upfront */
+  bytesTotal?: number
+  /** Name of the entry currently being processed */
+  currentEntry: string
+}
+
+/**
+ * Decompress every file of a zip held in a Buffer into memory.
+ *
+ * Returns an object mapping entry names to their content, in archive order.
+ * Directories and entries with unsafe names (Zip Slip) are skipped.
+ *
+ * # Arguments
+ * * `buffer` - Zip archive content
+ * * `options` - Read options
+ *   - `password`: Password of encrypted entries
+ */
+export declare function readAllFromBuffer(
+  buffer: Buffer,
+  options?: ReadEntryOptions | undefined | null,
+): Promise<Record<string, Buffer>>
+
+/**
+ * Read a single entry of a zip file into memory.
+ *
+ * Rejects with code `EntryNotFound` when the archive has no entry called `name`.
+ *
+ * # Arguments
+ * * `source_path` - Source zip file path
+ * * `name` - Entry name, as returned by `list()`
+ * * `options` - Read options
+ *   - `password`: Password of an encrypted entry
+ */
+export declare function readEntry(
+  sourcePath: string,
+  name: string,
+  options?: ReadEntryOptions | undefined | null,
+): Promise<Buffer>
+
+export interface ReadEntryOptions {
+  password?: string
+}
+
+/**
+ * Remove entries from a zip file, rewriting it without recompressing the other entries.
+ *
+ * Returns the number of entries removed.
+ *
+ * # Arguments
+ * * `zip_path` - Zip file path
+ * * `globs` - Glob patterns of the entries to remove, `!` negates and the last match wins
+ * * `options` - Remove options
+ *   - `matchOptions`: How `globs` match
+ */
+export declare function removeEntries(
+  zipPath: string,
+  globs: Array<string>,
+  options?: RemoveOptions | undefined | null,
+): Promise<number>
+
+export interface RemoveOptions {
+  matchOptions?: MatchOptions
+}
+
+/**
+ * Rename entries of a zip file, rewriting it without recompressing them.
+ *
+ * Returns the number of entries renamed. Rejects with code `EntryNotFound`
+ * when a name of `mapping` is not in the archive.
+ *
+ * # Arguments
+ * * `zip_path` - Zip file path
+ * * `mapping` - Entry name to its new name, a directory name ending with `/` renames everything inside it
+ */
+export declare function renameEntries(zipPath: string, mapping: Record<string, string>): Promise<number>
+
+/**
+ * How `zip()` handles symlinks found in the source directory.
+ *
+ * `preserve` stores the link itself with its target, `follow` stores what it
+ * points to, and `skip` leaves it out.
+ */
+export type Symlinks = 'preserve' | 'follow' | 'skip'
+
+/**
+ * Test the integrity of a zip file, like `unzip -t`.
+ *
+ * Reads every file entry through the decompressor and checks its CRC32 and size
+ * against the central directory. Nothing is written to disk. Failing entries are
+ * collected in the report instead of rejecting the promise.
+ *
+ * # Arguments
+ * * `source_path` - Source zip file path
+ * * `options` - Test options
+ *   - `password`: Password of encrypted entries
+ */
+export declare function test(sourcePath: string, options?: TestOptions | undefined | null): Promise<TestReport>
+
+export interface TestOptions {
+  password?: string
+}
+
+/** Result of `test()`. */
+export interface TestReport {
+  /** Number of file entries read through the decompressor */
+  entriesTested: number
+  /** Entries that failed, in archive order */
+  errors: Array<EntryError>
+}
+
 /**
  * Decompress a zip file into a directory.
  *
  * Automatically creates the output directory if it doesn't exist.
  * Safely handles paths to prevent writing outside the target directory (Zip Slip protection).
  * Restores file permissions on Unix systems.
+ * Restores symlinks whose target stays inside `output_dir` and skips the others.
+ * Restores modification times, from the extended timestamp field when an entry has one.
  *
  * # Arguments
  * * `source_path` - Source zip file path
  * * `output_dir` - Output directory path
+ * * `options` - Decompression options
+ *   - `onProgress`: Callback receiving throttled progress updates
+ *   - `signal`: AbortSignal that cancels the task and removes the files extracted so far
+ *   - `threads`: Number of worker threads extracting entries in parallel
+ *   - `password`: Password of encrypted entries, wrong passwords reject with code `InvalidPassword`
+ *   - `include`: Array of glob patterns, only matching entries are extracted
+ *   - `exclude`: Array of glob patterns to skip entries
+ *   - `matchOptions`: How `include` and `exclude` globs match
+ *   - `restoreMtimes`: Restore the modification times of files and directories (default: true)
+ */
+export declare function unzip(
+  sourcePath: string,
+  outputDir: string,
+  options?: UnzipOptions | undefined | null,
+): Promise<void>
+
+/**
+ * Decompress a zip held in a Buffer into a directory.
+ *
+ * Same behavior and options as `unzip()`, including the Zip Slip protection
+ * and permission restoring. The buffer is copied, so it can be reused right away.
+ *
+ * # Arguments
+ * * `buffer` - Zip archive content
+ * * `output_dir` - Output directory path
+ * * `options` - Same decompression options as `unzip()`
+ */
+export declare function unzipBuffer(
+  buffer: Buffer,
+  outputDir: string,
+  options?: UnzipOptions | undefined | null,
+): Promise<void>
+
+export interface UnzipOptions {
+  onProgress?: (progress: Progress) => void
+  signal?: AbortSignal
+  threads?: number
+  password?: string
+  include?: Array<string>
+  exclude?: Array<string>
+  matchOptions?: MatchOptions
+  restoreMtimes?: boolean
+}
+
+/**
+ * Decompress a zip read from a Node.js `Readable` into a directory, as the bytes arrive.
+ *
+ * Entries are read front to back from their local headers, without buffering the
+ * whole archive. Entries whose sizes follow their data in a data descriptor are
+ * supported for store and deflate. Same Zip Slip protection as `unzip()`; permissions
+ * and symlinks are restored once the central directory at the end of the stream has
+ * been read.
+ *
+ * # Arguments
+ * * `readable` - Readable emitting the zip archive as Buffers
+ * * `output_dir` - Output directory path
+ * * `options` - Decompression options
+ *   - `onProgress`: Callback receiving throttled progress updates, without totals
+ *   - `signal`: AbortSignal that cancels the task and removes the extracted files
+ *   - `include`: Glob patterns selecting the entries to extract
+ *   - `exclude`: Glob patterns of entries to skip
+ *   - `matchOptions`: How `include` and `exclude` globs match
+ *   - `restoreMtimes`: Restore the modification times of files and directories (default: true)
  */
-export declare function unzip(sourcePath: string, outputDir: string): Promise<void>
+export declare function unzipStream(
+  readable: import('node:stream').Readable,
+  outputDir: string,
+  options?: UnzipStreamOptions | undefined | null,
+): Promise<void>
+
+export interface UnzipStreamOptions {
+  onProgress?: (progress: Progress) => void
+  signal?: AbortSignal
+  include?: Array<string>
+  exclude?: Array<string>
+  matchOptions?: MatchOptions
+  restoreMtimes?: boolean
+}
+
+/** An in-memory entry for `zipToBuffer`. Names ending with `/` are directories. */
+export interface VirtualEntry {
+  name: string
+  content: Buffer | string
+  /** Unix permissions (default: 0o644 for files, 0o755 for directories) */
+  mode?: number
+  /** Modification time in milliseconds since the Unix epoch (default: now) */
+  mtime?: number
+}
 
 /**
- * Compress a directory into a zip file.
+ * Compress a directory, or a list of files, directories and globs, into a zip file.
  *
- * Returns the number of files compressed.
+ * Returns the number of files compressed, the added and replaced ones when updating an existing archive.
  *
  * # Arguments
- * * `source_dir` - Source directory path
+ * * `source` - Source directory path, or an array of `ZipSource`
  * * `output_path` - Output zip file path
  * * `options` - Compression options
- *   - `level`: Compression level (0-9, default: 1)
- *   - `exclude`: Array of glob patterns to exclude files
+ *   - `method`: Compression method (default: deflate)
+ *   - `level`: Compression level, range depends on `method` (deflate: 0-9, default: 1)
+ *   - `exclude`: Array of glob patterns to exclude files, `!` negates and the last match wins
+ *   - `include`: Array of glob patterns, only matching files are compressed
+ *   - `respectGitignore`: Skip what `.gitignore` files ignore, and `.git`
+ *   - `ignoreFiles`: Names of other ignore files read like `.gitignore`
+ *   - `excludeJunk`: Skip OS metadata files like `.DS_Store` and `Thumbs.db`
+ *   - `matchOptions`: How `include`, `exclude` and `overrides` globs match
+ *   - `onProgress`: Callback receiving throttled progress updates
+ *   - `signal`: AbortSignal that cancels the task and removes the partial zip file
+ *   - `threads`: Number of worker threads compressing entries in parallel, same bytes as sequential compression
+ *   - `autoStore`: Store already compressed file types without compression (default: true)
+ *   - `storeExtensions`: Extensions treated as already compressed, replacing the built-in list
+ *   - `detectIncompressible`: Also store files whose first block looks incompressible
+ *   - `overrides`: Glob to method or `{ method, level }`, the first matching glob wins
+ *   - `password`: Encrypts file entries with this password
+ *   - `encryption`: Encryption used with `password` (default: aes256)
+ *   - `symlinks`: Store symlinks as links (`preserve`), as what they point to (`follow`), or leave them out (`skip`) (default: follow)
+ *   - `deterministic`: Sort entries and fix their mtime and permissions, for byte-identical archives
+ *   - `prefix`: Directory every entry goes in, added after glob matching
+ *   - `mode`: What to do with an existing archive, `create` overwrites it (default: create)
  */
 export declare function zip(
-  sourceDir: string,
+  source: string | Array<ZipSource>,
   outputPath: string,
   options?: ZipOptions | undefined | null,
 ): Promise<number>
 
+/**
+ * What `zip()` does with an archive already at the output path.
+ *
+ * `create` overwrites it. Like Info-ZIP's `-g`, `-u` and `-f`, `append` adds
+ * every source and replaces entries of the same name, `update` only adds new
+ * and changed files, and `freshen` only replaces changed files.
+ */
+export type ZipMode = 'create' | 'append' | 'update' | 'freshen'
+
 export interface ZipOptions {
+  method?: Method
   level?: number
   exclude?: Array<string>
+  include?: Array<string>
+  respectGitignore?: boolean
+  ignoreFiles?: Array<string>
+  excludeJunk?: boolean
+  matchOptions?: MatchOptions
+  onProgress?: (progress: Progress) => void
+  signal?: AbortSignal
+  threads?: number
+  autoStore?: boolean
+  storeExtensions?: Array<string>
+  detectIncompressible?: boolean
+  overrides?: Record<string, Method | EntryOverride>
+  password?: string
+  encryption?: Encryption
+  symlinks?: Symlinks
+  deterministic?: boolean
+  prefix?: string
+  mode?: ZipMode
 }
+
+/** A file, directory or glob for `zip()`, and where it goes in the archive. */
+export interface ZipSource {
+  /** File, directory or glob pattern */
+  src: string
+  /**
+   * Archive name of a file, or the directory it goes in when ending with `/`.
+   * The directory the contents of directories and glob matches go in (default: the archive root)
+   */
+  dest?: string
+}
+
+/**
+ * Compress a directory, or a list of sources like `zip()` takes, into a zip streamed through a Node.js `Readable`.
+ *
+ * The archive is written without seeking, with sizes and CRC32 in data descriptors
+ * after each entry, so it can be piped straight into an HTTP response or an upload.
+ * Compression starts on the first read and pauses while the consumer applies
+ * backpressure. Failures, including an aborted `signal`, destroy the stream with
+ * the error. Destroying the stream stops the compression.
+ *
+ * # Arguments
+ * * `source` - Source directory path, or an array of `ZipSource`
+ * * `options` - Same compression options as `zip()`
+ */
+export declare function zipStream(
+  source: string | Array<ZipSource>,
+  options?: ZipOptions | undefined | null,
+): import('node:stream').Readable
+
+/**
+ * Compress a directory or a list of in-memory entries into a zip held in memory.
+ *
+ * Returns the archive as a Buffer, without touching the disk for the output.
+ *
+ * # Arguments
+ * * `source` - Source directory path, or an array of `VirtualEntry`
+ * * `options` - Same compression options as `zip()`
+ */
+export declare function zipToBuffer(
+  source: string | Array<VirtualEntry>,
+  options?: ZipOptions | undefined | null,
+): Promise<Buffer>
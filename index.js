@@ -572,5 +572,16 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.list = nativeBinding.list
+module.exports.mergeArchives = nativeBinding.mergeArchives
+module.exports.readAllFromBuffer = nativeBinding.readAllFromBuffer
+module.exports.readEntry = nativeBinding.readEntry
+module.exports.removeEntries = nativeBinding.removeEntries
+module.exports.renameEntries = nativeBinding.renameEntries
+module.exports.test = nativeBinding.test
 module.exports.unzip = nativeBinding.unzip
+module.exports.unzipBuffer = nativeBinding.unzipBuffer
+module.exports.unzipStream = nativeBinding.unzipStream
 module.exports.zip = nativeBinding.zip
+module.exports.zipStream = nativeBinding.zipStream
+module.exports.zipToBuffer = nativeBinding.zipToBuffer
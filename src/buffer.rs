@@ -0,0 +1,261 @@
+use indexmap::IndexMap;
+use napi::bindgen_prelude::{AsyncTask, Buffer, Either};
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
+use zip::ZipWriter;
+
+use crate::cancel::CancelToken;
+use crate::error;
+use crate::filter::{EntryFilter, NameFilter};
+use crate::read::{ReadEntryOptions, read_content};
+use crate::rules::EntryRules;
+use crate::time::dos_time;
+use crate::{
+  ArchiveInput, SourceEntry, UncompressTask, UnzipOptions, ZipOptions, check_threads, method,
+  open_entry, walk_entries, write_archive,
+};
+
+/// An in-memory entry for `zipToBuffer`. Names ending with `/` are directories.
+#[napi(object, object_to_js = false)]
+pub struct VirtualEntry {
+  pub name: String,
+  pub content: Either<Buffer, String>,
+  /// Unix permissions (default: 0o644 for files, 0o755 for directories)
+  pub mode: Option<u32>,
+  /// Modification time in milliseconds since the Unix epoch (default: now)
+  pub mtime: Option<i64>,
+}
+
+/// What `zipToBuffer` compresses.
+enum ArchiveSource {
+  Directory(PathBuf),
+  Entries(Vec<SourceEntry>),
+}
+
+pub struct ZipToBufferTask {
+  source: ArchiveSource,
+  pub options: ZipOptions,
+  pub(crate) rules: EntryRules,
+  pub(crate) filter: EntryFilter,
+}
+
+impl ZipToBufferTask {
+  fn compress(&self, cancel: &CancelToken) -> Result<Vec<u8>> {
+    let zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    let entries: Box<dyn Iterator<Item = Result<SourceEntry>>> = match &self.source {
+      ArchiveSource::Directory(source_dir) => {
+        Box::new(walk_entries(source_dir, "", &self.filter, &self.options))
+      }
+      ArchiveSource::Entries(entries) => Box::new(
+        entries
+          .iter()
+          .filter(|entry| {
+            let name = entry.name.trim_end_matches('/');
+            self.filter.is_selected(name) && !self.filter.is_pruned(name, !entry.is_file)
+          })
+          .cloned()
+          .map(Ok),
+      ),
+    };
+
+    let (cursor, _) = write_archive(zip, entries, &self.options, &self.rules, None, cancel)?;
+    Ok(cursor.into_inner())
+  }
+}
+
+impl Task for ZipToBufferTask {
+  type Output = Vec<u8>;
+  type JsValue = Buffer;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let cancel = self.options.signal.clone().unwrap_or_default();
+    self.compress(&cancel)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output.into())
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    error::reject(&env, err)
+  }
+}
+
+fn source_entry(entry: VirtualEntry) -> Result<SourceEntry> {
+  if entry.name.is_empty() {
+    return Err(Error::from_reason("Virtual entry name must not be empty"));
+  }
+  // The same names `unzip()` refuses to write, absolute or escaping the output directory
+  if entry.name.starts_with(['/', '\\'])
+    || entry
+      .name
+      .split(['/', '\\'])
+      .any(|part| part == ".." || part.contains(':'))
+  {
+    return Err(Error::from_reason(format!(
+      "Invalid virtual entry name '{}', it must be a relative path without '..'",
+      entry.name
+    )));
+  }
+  #[allow(clippy::collapsible_if)]
+  if let Some(mtime) = entry.mtime {
+    if dos_time(mtime).is_none() {
+      return Err(Error::from_reason(format!(
+        "Modification time of '{}' is outside the 1980-2107 range of zip timestamps",
+        entry.name
+      )));
+    }
+  }
+  let content: Arc<[u8]> = match entry.content {
+    Either::A(buffer) => Arc::from(&buffer[..]),
+    Either::B(string) => Arc::from(string.into_bytes()),
+  };
+  Ok(SourceEntry {
+    path: PathBuf::new(),
+    is_file: !entry.name.ends_with('/'),
+    name: entry.name,
+    content: Some(content),
+    mode: entry.mode,
+    mtime: entry.mtime.map(|mtime| mtime.div_euclid(1000)),
+    link_target: None,
+  })
+}
+
+/// Compress a directory or a list of in-memory entries into a zip held in memory.
+///
+/// Returns the archive as a Buffer, without touching the disk for the output.
+///
+/// # Arguments
+/// * `source` - Source directory path, or an array of `VirtualEntry`
+/// * `options` - Same compression options as `zip()`
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn zip_to_buffer(
+  source: Either<String, Vec<VirtualEntry>>,
+  options: Option<ZipOptions>,
+) -> Result<AsyncTask<ZipToBufferTask>> {
+  let opts = options.unwrap_or_default();
+
+  let rules = EntryRules::new(&opts)?;
+  let filter = EntryFilter::new(&opts)?;
+  check_threads(opts.threads)?;
+
+  let source = match source {
+    Either::A(source_dir) => ArchiveSource::Directory(PathBuf::from(source_dir)),
+    Either::B(entries) => ArchiveSource::Entries(
+      entries
+        .into_iter()
+        .map(source_entry)
+        .collect::<Result<_>>()?,
+    ),
+  };
+
+  Ok(AsyncTask::new(ZipToBufferTask {
+    source,
+    options: opts,
+    rules,
+    filter,
+  }))
+}
+
+/// Decompress a zip held in a Buffer into a directory.
+///
+/// Same behavior and options as `unzip()`, including the Zip Slip protection
+/// and permission restoring. The buffer is copied, so it can be reused right away.
+///
+/// # Arguments
+/// * `buffer` - Zip archive content
+/// * `output_dir` - Output directory path
+/// * `options` - Same decompression options as `unzip()`
+#[napi(ts_return_type = "Promise<void>")]
+pub fn unzip_buffer(
+  buffer: Buffer,
+  output_dir: String,
+  options: Option<UnzipOptions>,
+) -> Result<AsyncTask<UncompressTask>> {
+  let opts = options.unwrap_or_default();
+
+  check_threads(opts.threads)?;
+  let filter = NameFilter::new(
+    opts.include.as_deref(),
+    opts.exclude.as_deref(),
+    opts.match_options,
+  )?;
+
+  Ok(AsyncTask::new(UncompressTask {
+    source: ArchiveInput::Buffer(Arc::from(&buffer[..])),
+    output_dir: PathBuf::from(output_dir),
+    options: opts,
+    filter,
+  }))
+}
+
+pub struct ReadAllTask {
+  source: ArchiveInput,
+  pub options: ReadEntryOptions,
+}
+
+impl Task for ReadAllTask {
+  type Output = IndexMap<String, Vec<u8>>;
+  type JsValue = IndexMap<String, Buffer>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let mut archive = self.source.open()?;
+    let password = self.options.password.as_deref();
+
+    let mut files = IndexMap::new();
+    for i in 0..archive.len() {
+      let name = {
+        let file = archive
+          .by_index_raw(i)
+          .map_err(|e| Error::from_reason(format!("Failed to read zip entry: {}", e)))?;
+        // Skip directories and the same unsafe names `unzip()` refuses to write
+        if file.is_dir() || file.enclosed_name().is_none() {
+          continue;
+        }
+        method::check_supported(file.compression(), file.name())?;
+        file.name().to_string()
+      };
+
+      let content = read_content(open_entry(&mut archive, i, &name, password)?)?;
+      files.insert(name, content);
+    }
+    Ok(files)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(
+      output
+        .into_iter()
+        .map(|(name, content)| (name, content.into()))
+        .collect(),
+    )
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    error::reject(&env, err)
+  }
+}
+
+/// Decompress every file of a zip held in a Buffer into memory.
+///
+/// Returns an object mapping entry names to their content, in archive order.
+/// Directories and entries with unsafe names (Zip Slip) are skipped.
+///
+/// # Arguments
+/// * `buffer` - Zip archive content
+/// * `options` - Read options
+///   - `password`: Password of encrypted entries
+#[napi(ts_return_type = "Promise<Record<string, Buffer>>")]
+pub fn read_all_from_buffer(
+  buffer: Buffer,
+  options: Option<ReadEntryOptions>,
+) -> AsyncTask<ReadAllTask> {
+  AsyncTask::new(ReadAllTask {
+    source: ArchiveInput::Buffer(Arc::from(&buffer[..])),
+    options: options.unwrap_or_default(),
+  })
+}
@@ -0,0 +1,111 @@
+use napi::bindgen_prelude::{AbortSignal, FromNapiValue, Object, TypeName};
+use napi::{Error, Result, ValueType, sys};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::ErrorCode;
+
+/// Cancellation flag shared between a JS `AbortSignal` and the worker thread.
+///
+/// Converted from an `AbortSignal` by registering an abort callback, so the
+/// flag flips even while the task is already running on the libuv thread pool.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+  /// Cancels from the Rust side, e.g. when the consumer of a stream goes away.
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::Relaxed);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.0.load(Ordering::Relaxed)
+  }
+
+  /// Returns an `AbortError` if the signal has been aborted.
+  pub fn check(&self) -> Result<()> {
+    if self.is_cancelled() {
+      return Err(abort_error());
+    }
+    Ok(())
+  }
+}
+
+/// Error of aborted tasks, which `error::reject` gives the `AbortError` code.
+pub fn abort_error() -> Error {
+  ErrorCode::AbortError.error("AbortError".to_owned())
+}
+
+impl TypeName for CancelToken {
+  fn type_name() -> &'static str {
+    "AbortSignal"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Object
+  }
+}
+
+impl FromNapiValue for CancelToken {
+  unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> Result<Self> {
+    let signal = unsafe { Object::from_napi_value(env, napi_val)? };
+    let token = CancelToken::default();
+    if signal.get::<bool>("aborted")?.unwrap_or(false) {
+      token.0.store(true, Ordering::Relaxed);
+      return Ok(token);
+    }
+
+    let abort_signal = unsafe { AbortSignal::from_napi_value(env, napi_val)? };
+    let flag = token.0.clone();
+    abort_signal.on_abort(move || flag.store(true, Ordering::Relaxed));
+    Ok(token)
+  }
+}
+
+/// Records the paths an extraction creates, so an aborted task can remove them again.
+pub struct CreatedPaths {
+  enabled: bool,
+  paths: Vec<PathBuf>,
+}
+
+impl CreatedPaths {
+  pub fn new(enabled: bool) -> Self {
+    CreatedPaths {
+      enabled,
+      paths: Vec::new(),
+    }
+  }
+
+  /// `create_dir_all` that remembers every directory level it had to create.
+  pub fn create_dir_all(&mut self, dir: &Path) -> std::io::Result<()> {
+    if !self.enabled {
+      return std::fs::create_dir_all(dir);
+    }
+    let missing: Vec<PathBuf> = dir
+      .ancestors()
+      .take_while(|p| !p.as_os_str().is_empty() && !p.exists())
+      .map(Path::to_path_buf)
+      .collect();
+    std::fs::create_dir_all(dir)?;
+    self.paths.extend(missing.into_iter().rev());
+    Ok(())
+  }
+
+  pub fn record_file(&mut self, path: &Path) {
+    if self.enabled {
+      self.paths.push(path.to_path_buf());
+    }
+  }
+
+  /// Best-effort removal, children before their parents.
+  pub fn remove_all(&mut self) {
+    for path in self.paths.drain(..).rev() {
+      if path.is_dir() {
+        let _ = std::fs::remove_dir(&path);
+      } else {
+        let _ = std::fs::remove_file(&path);
+      }
+    }
+  }
+}
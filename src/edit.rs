@@ -0,0 +1,300 @@
+use indexmap::IndexMap;
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::{Path, PathBuf};
+use zip::{ZipArchive, ZipWriter};
+
+use crate::error::{self, ErrorCode};
+use crate::filter::{MatchOptions, NameFilter};
+use crate::open_archive;
+
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct RemoveOptions {
+  pub match_options: Option<MatchOptions>,
+}
+
+enum Edit {
+  /// Removes matching entries, and everything inside matching directories
+  Remove(Option<NameFilter>),
+  /// Entry names, and directory names ending with `/`, to their new name
+  Rename(IndexMap<String, String>),
+}
+
+pub struct EditTask {
+  pub zip_path: PathBuf,
+  edit: Edit,
+}
+
+impl EditTask {
+  /// New name of every entry, `None` for removed ones.
+  fn new_names(&self, names: &[String]) -> Result<Vec<Option<String>>> {
+    match &self.edit {
+      Edit::Remove(filter) => Ok(
+        names
+          .iter()
+          .map(|name| match filter {
+            Some(filter) if is_removed(filter, name) => None,
+            _ => Some(name.clone()),
+          })
+          .collect(),
+      ),
+      Edit::Rename(mapping) => rename(names, mapping),
+    }
+  }
+}
+
+impl Task for EditTask {
+  type Output = u32;
+  type JsValue = u32;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let names: Vec<String> = open_archive(&self.zip_path)?
+      .file_names()
+      .map(String::from)
+      .collect();
+    let new_names = self.new_names(&names)?;
+    let edited = names
+      .iter()
+      .zip(&new_names)
+      .filter(|(name, new_name)| new_name.as_ref() != Some(name))
+      .count() as u32;
+    if edited == 0 {
+      return Ok(0);
+    }
+
+    replace_archive(&self.zip_path, |archive, mut zip, _| {
+      for (index, new_name) in new_names.iter().enumerate() {
+        if let Some(new_name) = new_name {
+          copy_raw(archive, index, &mut zip, Some(new_name))?;
+        }
+      }
+      let writer = zip
+        .finish()
+        .map_err(|e| Error::from_reason(format!("Zip finalization failed: {}", e)))?;
+      Ok((writer, edited))
+    })
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    error::reject(&env, err)
+  }
+}
+
+/// Whether `name` or one of its parent directories is selected by `filter`.
+fn is_removed(filter: &NameFilter, name: &str) -> bool {
+  // Directories are matched without their trailing slash
+  let name = name.trim_end_matches('/');
+  name
+    .match_indices('/')
+    .map(|(end, _)| &name[..end])
+    .chain(std::iter::once(name))
+    .any(|path| filter.is_selected(path))
+}
+
+/// Applies `mapping` to `names`, rejecting names it doesn't find and names taken twice.
+///
+/// An exact match wins, else the longest directory key holding the entry.
+fn rename(names: &[String], mapping: &IndexMap<String, String>) -> Result<Vec<Option<String>>> {
+  if let Some(missing) = mapping.keys().find(|key| {
+    !names
+      .iter()
+      .any(|name| name == *key || (key.ends_with('/') && name.starts_with(key.as_str())))
+  }) {
+    return Err(
+      ErrorCode::EntryNotFound.error(format!("Entry '{}' not found in zip archive", missing)),
+    );
+  }
+
+  let new_names: Vec<String> = names
+    .iter()
+    .map(|name| {
+      if let Some(new_name) = mapping.get(name) {
+        return new_name.clone();
+      }
+      mapping
+        .iter()
+        .filter(|(key, _)| key.ends_with('/') && name.starts_with(key.as_str()))
+        .max_by_key(|(key, _)| key.len())
+        .map(|(key, new_dir)| format!("{}{}", new_dir, &name[key.len()..]))
+        .unwrap_or_else(|| name.clone())
+    })
+    .collect();
+
+  let mut taken: HashMap<&str, &str> = HashMap::new();
+  for (name, new_name) in names.iter().zip(&new_names) {
+    match taken.entry(new_name) {
+      Entry::Vacant(vacant) => {
+        vacant.insert(name);
+      }
+      Entry::Occupied(occupied) => {
+        return Err(Error::from_reason(format!(
+          "Entries '{}' and '{}' would both be named '{}'",
+          occupied.get(),
+          name,
+          new_name
+        )));
+      }
+    }
+  }
+  Ok(new_names.into_iter().map(Some).collect())
+}
+
+/// Normalizes a new entry name, which directories keep ending with `/`.
+fn entry_name(old_name: &str, new_name: &str) -> Result<String> {
+  // Normalize path separator to / on Windows
+  let name = new_name.replace('\\', "/");
+  let name = name.trim_start_matches('/');
+  if name.is_empty() || name.split('/').any(|part| part == "..") {
+    return Err(Error::from_reason(format!(
+      "Invalid name '{}' for '{}', it must be a relative path without '..'",
+      new_name, old_name
+    )));
+  }
+  match old_name.ends_with('/') && !name.ends_with('/') {
+    true => Ok(format!("{}/", name)),
+    false => Ok(name.to_string()),
+  }
+}
+
+/// Writes a new version of the archive at `path` to a temp file next to it, which then replaces it.
+///
+/// `write` gets the current archive, a writer holding its comment and the temp path,
+/// and returns what the finished writer gives back.
+pub(crate) fn replace_archive<T>(
+  path: &Path,
+  write: impl FnOnce(
+    &mut ZipArchive<File>,
+    ZipWriter<BufWriter<File>>,
+    &Path,
+  ) -> Result<(BufWriter<File>, T)>,
+) -> Result<T> {
+  write_through_temp(path, |temp_path| write_temp(path, temp_path, write))
+}
+
+/// Lets `write` create the file at `path` through a temp file next to it, so that
+/// `path` only changes once the file is complete.
+pub(crate) fn write_through_temp<T>(
+  path: &Path,
+  write: impl FnOnce(&Path) -> Result<T>,
+) -> Result<T> {
+  let mut temp_path = OsString::from(path);
+  temp_path.push(".rewrite");
+  let temp_path = PathBuf::from(temp_path);
+
+  let result = write(&temp_path).and_then(|value| {
+    std::fs::rename(&temp_path, path)
+      .map_err(|e| Error::from_reason(format!("Failed to replace zip file: {}", e)))?;
+    Ok(value)
+  });
+  if result.is_err() {
+    let _ = std::fs::remove_file(&temp_path);
+  }
+  result
+}
+
+fn write_temp<T>(
+  path: &Path,
+  temp_path: &Path,
+  write: impl FnOnce(
+    &mut ZipArchive<File>,
+    ZipWriter<BufWriter<File>>,
+    &Path,
+  ) -> Result<(BufWriter<File>, T)>,
+) -> Result<T> {
+  let mut archive = open_archive(path)?;
+  let file = File::create(temp_path)
+    .map_err(|e| Error::from_reason(format!("Failed to create zip file: {}", e)))?;
+  let mut zip = ZipWriter::new(BufWriter::with_capacity(65536, file));
+  zip.set_raw_comment(archive.comment().into());
+
+  let (mut writer, value) = write(&mut archive, zip, temp_path)?;
+  writer
+    .flush()
+    .map_err(|e| Error::from_reason(format!("Zip finalization failed: {}", e)))?;
+  Ok(value)
+}
+
+/// Copies entry `index` into `zip` as is, without decompressing it, under `name` when given.
+pub(crate) fn copy_raw<W: Write + Seek>(
+  archive: &mut ZipArchive<File>,
+  index: usize,
+  zip: &mut ZipWriter<W>,
+  name: Option<&str>,
+) -> Result<()> {
+  let file = archive
+    .by_index_raw(index)
+    .map_err(|e| Error::from_reason(format!("Failed to read zip entry: {}", e)))?;
+  let result = match name {
+    Some(name) if name != file.name() => zip.raw_copy_file_rename(file, name),
+    _ => zip.raw_copy_file(file),
+  };
+  result.map_err(|e| Error::from_reason(format!("Failed to copy zip entry: {}", e)))
+}
+
+/// Remove entries from a zip file, rewriting it without recompressing the other entries.
+///
+/// Returns the number of entries removed.
+///
+/// # Arguments
+/// * `zip_path` - Zip file path
+/// * `globs` - Glob patterns of the entries to remove, `!` negates and the last match wins
+/// * `options` - Remove options
+///   - `matchOptions`: How `globs` match
+#[napi(ts_return_type = "Promise<number>")]
+pub fn remove_entries(
+  zip_path: String,
+  globs: Vec<String>,
+  options: Option<RemoveOptions>,
+) -> Result<AsyncTask<EditTask>> {
+  let options = options.unwrap_or_default();
+  // An empty include list would select everything
+  let filter = match globs.is_empty() {
+    true => None,
+    false => Some(NameFilter::matching(
+      "remove",
+      &globs,
+      options.match_options,
+    )?),
+  };
+  Ok(AsyncTask::new(EditTask {
+    zip_path: PathBuf::from(zip_path),
+    edit: Edit::Remove(filter),
+  }))
+}
+
+/// Rename entries of a zip file, rewriting it without recompressing them.
+///
+/// Returns the number of entries renamed. Rejects with code `EntryNotFound`
+/// when a name of `mapping` is not in the archive.
+///
+/// # Arguments
+/// * `zip_path` - Zip file path
+/// * `mapping` - Entry name to its new name, a directory name ending with `/` renames everything inside it
+#[napi(ts_return_type = "Promise<number>")]
+pub fn rename_entries(
+  zip_path: String,
+  mapping: IndexMap<String, String>,
+) -> Result<AsyncTask<EditTask>> {
+  let mapping = mapping
+    .into_iter()
+    .map(|(name, new_name)| {
+      let new_name = entry_name(&name, &new_name)?;
+      Ok((name, new_name))
+    })
+    .collect::<Result<_>>()?;
+  Ok(AsyncTask::new(EditTask {
+    zip_path: PathBuf::from(zip_path),
+    edit: Edit::Rename(mapping),
+  }))
+}
@@ -0,0 +1,63 @@
+use napi::{Error, Result};
+use napi_derive::napi;
+use zip::unstable::write::FileOptionsExt;
+use zip::write::FullFileOptions;
+
+/// Encryption used for file entries when a `password` is set.
+///
+/// The AES modes are only available when the `aes-crypto` cargo feature is enabled.
+/// `zipcrypto` is weak and only meant for tools that can't read AES archives.
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encryption {
+  Aes128,
+  Aes192,
+  Aes256,
+  Zipcrypto,
+}
+
+impl Encryption {
+  pub fn name(self) -> &'static str {
+    match self {
+      Encryption::Aes128 => "aes128",
+      Encryption::Aes192 => "aes192",
+      Encryption::Aes256 => "aes256",
+      Encryption::Zipcrypto => "zipcrypto",
+    }
+  }
+
+  /// Checks that the encryption is compiled in.
+  pub fn validate(self) -> Result<()> {
+    if self != Encryption::Zipcrypto && !cfg!(feature = "aes-crypto") {
+      return Err(Error::from_reason(format!(
+        "Encryption '{}' is not available in this build",
+        self.name()
+      )));
+    }
+    Ok(())
+  }
+
+  /// Encrypts entries written with `options` using `password`.
+  pub fn apply<'k>(
+    self,
+    options: FullFileOptions<'static>,
+    password: &'k str,
+  ) -> FullFileOptions<'k> {
+    #[cfg(feature = "aes-crypto")]
+    {
+      use zip::AesMode;
+      let mode = match self {
+        Encryption::Aes128 => AesMode::Aes128,
+        Encryption::Aes192 => AesMode::Aes192,
+        Encryption::Aes256 => AesMode::Aes256,
+        Encryption::Zipcrypto => return options.with_deprecated_encryption(password.as_bytes()),
+      };
+      options.with_aes_encryption(mode, password)
+    }
+    #[cfg(not(feature = "aes-crypto"))]
+    {
+      // AES modes are rejected by `validate`
+      options.with_deprecated_encryption(password.as_bytes())
+    }
+  }
+}
@@ -0,0 +1,76 @@
+use napi::bindgen_prelude::JsValue;
+use napi::{Env, Error, Result, Status};
+use zip::result::ZipError;
+
+/// Failures callers are expected to branch on, exposed as the JS error `code`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+  InvalidPassword,
+  PasswordRequired,
+  EntryNotFound,
+  /// The task's `signal` was aborted
+  AbortError,
+}
+
+impl ErrorCode {
+  const ALL: [ErrorCode; 4] = [
+    ErrorCode::InvalidPassword,
+    ErrorCode::PasswordRequired,
+    ErrorCode::EntryNotFound,
+    ErrorCode::AbortError,
+  ];
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      ErrorCode::InvalidPassword => "InvalidPassword",
+      ErrorCode::PasswordRequired => "PasswordRequired",
+      ErrorCode::EntryNotFound => "EntryNotFound",
+      ErrorCode::AbortError => "AbortError",
+    }
+  }
+
+  /// Builds an error carrying this code.
+  ///
+  /// Errors built on the worker thread only hold a status, so the code rides
+  /// along as the cause until `reject` turns it into the `code` property.
+  pub fn error(self, reason: String) -> Error {
+    let mut error = Error::from_reason(reason);
+    error.set_cause(Error::new(Status::GenericFailure, self.as_str()));
+    error
+  }
+
+  fn of(error: &Error) -> Option<ErrorCode> {
+    let cause = error.cause.as_ref()?;
+    Self::ALL
+      .into_iter()
+      .find(|code| cause.reason == code.as_str())
+  }
+}
+
+/// `Task::reject` for tasks that can fail with an `ErrorCode`.
+pub fn reject<T>(env: &Env, error: Error) -> Result<T> {
+  Err(with_code(env, error)?)
+}
+
+/// Turns the `ErrorCode` of `error`, if any, into the `code` property of a JS error.
+pub fn with_code(env: &Env, error: Error) -> Result<Error> {
+  let Some(code) = ErrorCode::of(&error) else {
+    return Ok(error);
+  };
+  let mut object = env.create_error(Error::from_reason(error.reason))?;
+  object.set("code", code.as_str())?;
+  Ok(Error::from(object.to_unknown()))
+}
+
+/// Maps a failure to open an entry, singling out password problems.
+pub fn entry_error(error: ZipError, name: &str) -> Error {
+  match error {
+    ZipError::InvalidPassword => {
+      ErrorCode::InvalidPassword.error(format!("Wrong password for entry '{}'", name))
+    }
+    ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED) => ErrorCode::PasswordRequired.error(
+      format!("Entry '{}' is encrypted, a password is required", name),
+    ),
+    e => Error::from_reason(format!("Failed to read zip entry: {}", e)),
+  }
+}
@@ -0,0 +1,384 @@
+use glob::Pattern;
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::path::Path;
+
+use crate::ZipOptions;
+
+/// OS metadata files left out by `excludeJunk`, in .gitignore syntax
+#[rustfmt::skip]
+const JUNK: &[&str] = &[
+  // macOS
+  ".DS_Store", "._*", ".AppleDouble", ".LSOverride", ".Spotlight-V100", ".Trashes", ".fseventsd",
+  "__MACOSX",
+  // Windows
+  "Thumbs.db", "ehthumbs.db", "[Dd]esktop.ini", "$RECYCLE.BIN",
+];
+
+/// Ignore file rules match like git: `*` and `?` don't cross `/`
+const IGNORE_MATCH: glob::MatchOptions = glob::MatchOptions {
+  case_sensitive: true,
+  require_literal_separator: true,
+  require_literal_leading_dot: false,
+};
+
+/// How `include`, `exclude` and `overrides` globs match names.
+#[napi(object)]
+#[derive(Clone, Copy, Default)]
+pub struct MatchOptions {
+  /// Match letters case-sensitively (default: true)
+  pub case_sensitive: Option<bool>,
+  /// Only match `/` with a literal `/`, so `*` stays within a directory (default: false)
+  pub require_literal_separator: Option<bool>,
+  /// Only match a leading `.` of a file name with a literal `.` (default: false)
+  pub require_literal_leading_dot: Option<bool>,
+}
+
+impl From<MatchOptions> for glob::MatchOptions {
+  fn from(options: MatchOptions) -> Self {
+    glob::MatchOptions {
+      case_sensitive: options.case_sensitive.unwrap_or(true),
+      require_literal_separator: options.require_literal_separator.unwrap_or(false),
+      require_literal_leading_dot: options.require_literal_leading_dot.unwrap_or(false),
+    }
+  }
+}
+
+/// Compiles the `kind` glob `glob`, pointing at the position of a syntax error.
+pub(crate) fn compile_pattern(kind: &str, glob: &str) -> Result<Pattern> {
+  Pattern::new(glob).map_err(|e| pattern_error(kind, glob, e.pos, e.msg))
+}
+
+fn pattern_error(kind: &str, glob: &str, pos: usize, msg: &str) -> Error {
+  Error::from_reason(format!(
+    "Invalid {} pattern '{}' at position {}: {}",
+    kind, glob, pos, msg
+  ))
+}
+
+/// Globs where a `!` prefix negates a glob, and the last matching glob wins.
+struct PatternList(Vec<(Pattern, bool)>);
+
+impl PatternList {
+  fn new(kind: &str, patterns: Option<&[String]>) -> Result<Self> {
+    let patterns = patterns
+      .unwrap_or_default()
+      .iter()
+      .map(|p| {
+        let (glob, positive) = match p.strip_prefix('!') {
+          Some(negated) => (negated, false),
+          None => (p.as_str(), true),
+        };
+        // Positions count the `!` prefix too
+        let pattern = Pattern::new(glob)
+          .map_err(|e| pattern_error(kind, p, p.len() - glob.len() + e.pos, e.msg))?;
+        Ok((pattern, positive))
+      })
+      .collect::<Result<_>>()?;
+    Ok(PatternList(patterns))
+  }
+
+  fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  /// Whether a negated glob could match a name inside the directory `dir`, ending with `/`,
+  /// judging by the part of the glob before its first wildcard.
+  fn may_negate_below(&self, dir: &str, options: glob::MatchOptions) -> bool {
+    let fold = |s: &str| match options.case_sensitive {
+      true => s.to_string(),
+      false => s.to_lowercase(),
+    };
+    let dir = fold(dir);
+    self.0.iter().any(|(pattern, positive)| {
+      let glob = pattern.as_str();
+      let literal = fold(&glob[..glob.find(['*', '?', '[']).unwrap_or(glob.len())]);
+      !positive && (literal.starts_with(&dir) || dir.starts_with(&literal))
+    })
+  }
+
+  /// Whether the last glob matching `name` is a positive one, `None` when none matches.
+  fn last_match(&self, name: &str, options: glob::MatchOptions) -> Option<bool> {
+    self
+      .0
+      .iter()
+      .rev()
+      .find(|(pattern, _)| pattern.matches_with(name, options))
+      .map(|&(_, positive)| positive)
+  }
+}
+
+/// Selects entry names with `include` and `exclude` globs.
+pub(crate) struct NameFilter {
+  include: PatternList,
+  exclude: PatternList,
+  options: glob::MatchOptions,
+}
+
+impl NameFilter {
+  /// Compiles the globs, rejecting invalid ones.
+  pub fn new(
+    include: Option<&[String]>,
+    exclude: Option<&[String]>,
+    options: Option<MatchOptions>,
+  ) -> Result<Self> {
+    Ok(NameFilter {
+      include: PatternList::new("include", include)?,
+      exclude: PatternList::new("exclude", exclude)?,
+      options: options.unwrap_or_default().into(),
+    })
+  }
+
+  /// Selects the names matching the `kind` globs `globs`, with `!` negation.
+  pub fn matching(kind: &str, globs: &[String], options: Option<MatchOptions>) -> Result<Self> {
+    Ok(NameFilter {
+      include: PatternList::new(kind, Some(globs))?,
+      exclude: PatternList::new("exclude", None)?,
+      options: options.unwrap_or_default().into(),
+    })
+  }
+
+  /// Matches `name` against the include and exclude globs, directories without their trailing slash.
+  pub fn is_selected(&self, name: &str) -> bool {
+    let included =
+      self.include.is_empty() || self.include.last_match(name, self.options) == Some(true);
+    included && self.exclude.last_match(name, self.options) != Some(true)
+  }
+
+  /// Whether an `exclude` glob matches the directory `name` or everything inside it,
+  /// e.g. `node_modules/**`, so that it is skipped with its contents.
+  ///
+  /// Globs matching the contents only prune the directory when no negated glob
+  /// could re-include something inside it, e.g. `!dist/keep.txt` for `dist/**`.
+  fn excludes_dir(&self, name: &str) -> bool {
+    if self.exclude.last_match(name, self.options) == Some(true) {
+      return true;
+    }
+    let dir = format!("{}/", name);
+    self.exclude.last_match(&dir, self.options) == Some(true)
+      && !self.exclude.may_negate_below(&dir, self.options)
+  }
+}
+
+/// A line of an ignore file.
+struct IgnoreRule {
+  pattern: Pattern,
+  negated: bool,
+  /// A trailing `/` only matches directories
+  dir_only: bool,
+  /// Rules with a `/` match the path from the ignore file's directory, others the file name
+  anchored: bool,
+}
+
+impl IgnoreRule {
+  fn parse(line: &str) -> Option<Self> {
+    let line = trim_unescaped_spaces(line.trim_end_matches('\r'));
+    if line.is_empty() || line.starts_with('#') {
+      return None;
+    }
+    let (line, negated) = match line.strip_prefix('!') {
+      Some(line) => (line, true),
+      None => (line, false),
+    };
+    let (line, dir_only) = match line.strip_suffix('/') {
+      Some(line) => (line, true),
+      None => (line, false),
+    };
+    let anchored = line.contains('/');
+    let line = line.strip_prefix('/').unwrap_or(line);
+    if line.is_empty() {
+      return None;
+    }
+    Some(IgnoreRule {
+      pattern: Pattern::new(&unescape(line)).ok()?,
+      negated,
+      dir_only,
+      anchored,
+    })
+  }
+}
+
+/// Rules of one ignore file, relative to the directory holding it.
+struct IgnoreFile {
+  /// Source-relative path of the directory with a trailing `/`, empty for the root
+  base: String,
+  rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+  fn parse<'a>(base: String, lines: impl Iterator<Item = &'a str>) -> Self {
+    IgnoreFile {
+      base,
+      rules: lines.filter_map(IgnoreRule::parse).collect(),
+    }
+  }
+
+  /// `Some(true)` when the last matching rule ignores `name`, `Some(false)` when it re-includes it.
+  fn last_match(&self, name: &str, is_dir: bool) -> Option<bool> {
+    let path = name.strip_prefix(self.base.as_str())?;
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    self
+      .rules
+      .iter()
+      .rev()
+      .find(|rule| {
+        (is_dir || !rule.dir_only)
+          && match rule.anchored {
+            true => rule.pattern.matches_with(path, IGNORE_MATCH),
+            false => rule.pattern.matches_with(file_name, IGNORE_MATCH),
+          }
+      })
+      .map(|rule| !rule.negated)
+  }
+}
+
+/// Selects the entries `zip()` writes from `include`, `exclude`, `excludeJunk` and ignore files.
+pub(crate) struct EntryFilter {
+  names: NameFilter,
+  junk: Option<IgnoreFile>,
+  ignore_files: Vec<String>,
+}
+
+impl EntryFilter {
+  /// Compiles the globs of `options`, rejecting invalid ones.
+  pub fn new(options: &ZipOptions) -> Result<Self> {
+    let mut ignore_files = options.ignore_files.clone().unwrap_or_default();
+    let mut junk = Vec::new();
+    if options.exclude_junk.unwrap_or(false) {
+      junk.extend_from_slice(JUNK);
+    }
+    if options.respect_gitignore.unwrap_or(false) {
+      if !ignore_files.iter().any(|f| f == ".gitignore") {
+        ignore_files.push(".gitignore".to_string());
+      }
+      junk.push(".git");
+    }
+
+    Ok(EntryFilter {
+      names: NameFilter::new(
+        options.include.as_deref(),
+        options.exclude.as_deref(),
+        options.match_options,
+      )?,
+      junk: (!junk.is_empty()).then(|| IgnoreFile::parse(String::new(), junk.into_iter())),
+      ignore_files,
+    })
+  }
+
+  /// See [`NameFilter::is_selected`].
+  pub fn is_selected(&self, name: &str) -> bool {
+    self.names.is_selected(name)
+  }
+
+  /// Whether `name` or one of its parent directories is an excluded directory or junk,
+  /// for entries that don't come from a walk.
+  pub fn is_pruned(&self, name: &str, is_dir: bool) -> bool {
+    let name = name.trim_end_matches('/');
+    let parents = name.match_indices('/').map(|(i, _)| (&name[..i], true));
+    parents
+      .chain(std::iter::once((name, is_dir)))
+      .any(|(path, is_dir)| {
+        (is_dir && self.names.excludes_dir(path))
+          || self
+            .junk
+            .as_ref()
+            .is_some_and(|junk| junk.last_match(path, is_dir) == Some(true))
+      })
+  }
+
+  /// Starts tracking the ignore files of a walk whose entry names start with `prefix`.
+  pub fn ignore_stack(&self, prefix: &str) -> IgnoreStack<'_> {
+    IgnoreStack {
+      filter: self,
+      prefix: prefix.to_string(),
+      files: Vec::new(),
+    }
+  }
+}
+
+/// Ignore files of the directories on the current walk path, with the depth of their directory.
+pub(crate) struct IgnoreStack<'a> {
+  filter: &'a EntryFilter,
+  /// Archive prefix of the walked names, ignore files only see the part after it
+  prefix: String,
+  files: Vec<(usize, IgnoreFile)>,
+}
+
+impl IgnoreStack<'_> {
+  /// Whether the walk keeps the source-relative path `name` and, for a directory, descends into it.
+  ///
+  /// Reads the ignore files of every kept directory. Like git, nothing inside
+  /// an ignored directory, or a directory an `exclude` glob matches, can be
+  /// re-included.
+  pub fn allows(&mut self, path: &Path, name: &str, depth: usize, is_dir: bool) -> bool {
+    // Drop the ignore files of directories the walk has left
+    self.files.retain(|&(file_depth, _)| file_depth < depth);
+
+    if !name.is_empty() {
+      if is_dir
+        && self
+          .filter
+          .names
+          .excludes_dir(&format!("{}{}", self.prefix, name))
+      {
+        return false;
+      }
+      let ignored = self
+        .filter
+        .junk
+        .iter()
+        .chain(self.files.iter().map(|(_, file)| file))
+        .filter_map(|file| file.last_match(name, is_dir))
+        .next_back();
+      if ignored == Some(true) {
+        return false;
+      }
+    }
+
+    if is_dir {
+      let base = if name.is_empty() {
+        String::new()
+      } else {
+        format!("{}/", name)
+      };
+      for ignore_file in &self.filter.ignore_files {
+        // Unreadable ignore files are skipped, like git does
+        if let Ok(content) = std::fs::read_to_string(path.join(ignore_file)) {
+          let file = IgnoreFile::parse(base.clone(), content.lines());
+          self.files.push((depth, file));
+        }
+      }
+    }
+    true
+  }
+}
+
+/// Drops trailing spaces, unless they are escaped with a backslash.
+fn trim_unescaped_spaces(line: &str) -> &str {
+  let trimmed = line.trim_end_matches(' ');
+  if trimmed.ends_with('\\') && trimmed.len() < line.len() {
+    return &line[..trimmed.len() + 1];
+  }
+  trimmed
+}
+
+/// Turns backslash escapes into ones `glob` understands, e.g. `\*` into `[*]`.
+fn unescape(line: &str) -> String {
+  let mut pattern = String::with_capacity(line.len());
+  let mut chars = line.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some(escaped @ ('*' | '?' | '[' | ']')) => {
+          pattern.push('[');
+          pattern.push(escaped);
+          pattern.push(']');
+        }
+        Some(escaped) => pattern.push(escaped),
+        None => {}
+      },
+      _ => pattern.push(c),
+    }
+  }
+  pattern
+}
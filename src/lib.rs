@@ -1,261 +1,948 @@
 #![deny(clippy::all)]
 
-use glob::Pattern;
-use napi::bindgen_prelude::AsyncTask;
+use napi::bindgen_prelude::{AsyncTask, Either};
 use napi::{Env, Error, Result, Task};
 use napi_derive::napi;
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{BufWriter, Read, Write};
-use std::path::PathBuf;
+use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use walkdir::WalkDir;
 
-use zip::CompressionMethod;
-use zip::write::SimpleFileOptions;
-
-#[napi(object)]
+use zip::read::ZipFile;
+use zip::write::FullFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+mod buffer;
+mod cancel;
+mod edit;
+mod encryption;
+mod error;
+mod filter;
+mod list;
+mod local;
+mod merge;
+mod method;
+mod parallel;
+mod progress;
+mod read;
+mod rules;
+mod source;
+mod stream;
+mod symlink;
+mod time;
+mod update;
+mod verify;
+
+pub use buffer::{
+  ReadAllTask, VirtualEntry, ZipToBufferTask, read_all_from_buffer, unzip_buffer, zip_to_buffer,
+};
+pub use cancel::CancelToken;
+use cancel::CreatedPaths;
+pub use edit::{EditTask, RemoveOptions, remove_entries, rename_entries};
+pub use encryption::Encryption;
+pub use filter::MatchOptions;
+use filter::{EntryFilter, NameFilter};
+pub use list::{EntryInfo, ListTask, list};
+pub use merge::{MergeOptions, MergeTask, OnConflict, merge_archives};
+pub use method::Method;
+use progress::ProgressReporter;
+pub use progress::{Progress, ProgressCallback};
+pub use read::{ReadEntryOptions, ReadEntryTask, read_entry};
+use rules::EntryRules;
+pub use rules::{EntryOverride, Overrides};
+use source::Sources;
+pub use source::ZipSource;
+pub use stream::{UnzipStreamOptions, unzip_stream, zip_stream};
+pub use symlink::Symlinks;
+use time::{
+  EXTENDED_TIMESTAMP_ID, archive_mtime, dos_time, extended_timestamp, set_dir_mtime, system_time,
+  unix_seconds,
+};
+pub use update::ZipMode;
+use update::update_archive;
+pub use verify::{EntryError, TestOptions, TestReport, TestTask, test};
+
+/// File entries from this size on get Zip64 fields, below 4 GiB as compressing
+/// incompressible data makes it slightly larger
+const ZIP64_THRESHOLD: u64 = 0xF000_0000;
+
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
 pub struct ZipOptions {
+  pub method: Option<Method>,
   pub level: Option<i32>,
   pub exclude: Option<Vec<String>>,
+  pub include: Option<Vec<String>>,
+  pub respect_gitignore: Option<bool>,
+  pub ignore_files: Option<Vec<String>>,
+  pub exclude_junk: Option<bool>,
+  pub match_options: Option<MatchOptions>,
+  #[napi(ts_type = "(progress: Progress) => void")]
+  pub on_progress: Option<ProgressCallback>,
+  #[napi(ts_type = "AbortSignal")]
+  pub signal: Option<CancelToken>,
+  pub threads: Option<u32>,
+  pub auto_store: Option<bool>,
+  pub store_extensions: Option<Vec<String>>,
+  pub detect_incompressible: Option<bool>,
+  #[napi(ts_type = "Record<string, Method | EntryOverride>")]
+  pub overrides: Option<Overrides>,
+  pub password: Option<String>,
+  pub encryption: Option<Encryption>,
+  pub symlinks: Option<Symlinks>,
+  pub deterministic: Option<bool>,
+  pub prefix: Option<String>,
+  pub mode: Option<ZipMode>,
 }
 
 pub struct CompressTask {
-  pub source_dir: PathBuf,
+  pub(crate) sources: Sources,
   pub output_path: PathBuf,
   pub options: ZipOptions,
+  pub(crate) rules: EntryRules,
+  pub(crate) filter: EntryFilter,
 }
 
-impl Task for CompressTask {
-  type Output = u32;
-  type JsValue = u32;
+/// A file or directory selected for the archive.
+#[derive(Clone)]
+pub(crate) struct SourceEntry {
+  /// Source path on disk, empty for virtual entries
+  pub path: PathBuf,
+  pub name: String,
+  pub is_file: bool,
+  /// Content of a virtual entry, read instead of `path`
+  pub content: Option<Arc<[u8]>>,
+  /// Unix permissions, read from `path` when unset
+  pub mode: Option<u32>,
+  /// Modification time in seconds since the Unix epoch, read from `path` when unset
+  pub mtime: Option<i64>,
+  /// Target of a preserved symlink, stored as its content
+  pub link_target: Option<String>,
+}
 
-  fn compute(&mut self) -> Result<Self::Output> {
-    // 1. Create file stream with buffer
-    let file = File::create(&self.output_path)
+impl SourceEntry {
+  /// A file or directory found on disk.
+  pub fn from_path(path: PathBuf, name: String, is_file: bool) -> Self {
+    SourceEntry {
+      path,
+      name,
+      is_file,
+      content: None,
+      mode: None,
+      mtime: None,
+      link_target: None,
+    }
+  }
+
+  /// Uncompressed size of a file entry.
+  pub fn size(&self) -> Result<u64> {
+    if let Some(content) = &self.content {
+      return Ok(content.len() as u64);
+    }
+    std::fs::metadata(&self.path)
+      .map(|metadata| metadata.len())
+      .map_err(|e| Error::from_reason(format!("Failed to read source file: {}", e)))
+  }
+
+  /// Whether a file entry may need Zip64 fields for its sizes.
+  pub fn is_large(&self) -> bool {
+    self.size().is_ok_and(|size| size >= ZIP64_THRESHOLD)
+  }
+
+  /// Opens the content of a file entry.
+  pub fn open(&self) -> Result<Box<dyn Read + '_>> {
+    if let Some(content) = &self.content {
+      return Ok(Box::new(&content[..]));
+    }
+    let file = File::open(&self.path)
+      .map_err(|e| Error::from_reason(format!("Failed to read source file: {}", e)))?;
+    Ok(Box::new(file))
+  }
+}
+
+impl CompressTask {
+  fn compress(&self, cancel: &CancelToken) -> Result<u32> {
+    match self.options.mode.unwrap_or(ZipMode::Create) {
+      ZipMode::Create => {}
+      ZipMode::Append | ZipMode::Update if !self.output_path.exists() => {}
+      mode => return update_archive(self, mode, cancel),
+    }
+
+    // Written next to the output first, so a failed or aborted task keeps an existing archive
+    edit::write_through_temp(&self.output_path, |temp_path| {
+      self.create(temp_path, cancel)
+    })
+  }
+
+  fn create(&self, temp_path: &Path, cancel: &CancelToken) -> Result<u32> {
+    // Create file stream with buffer
+    let file = File::create(temp_path)
       .map_err(|e| Error::from_reason(format!("Failed to create zip file: {}", e)))?;
 
     // 64KB write buffer
     let buf_writer = BufWriter::with_capacity(65536, file);
-    let mut zip = zip::ZipWriter::new(buf_writer);
-
-    // Parse exclude patterns
-    let exclude_patterns: Vec<Pattern> = self
-      .options
-      .exclude
-      .as_ref()
-      .map(|patterns| {
-        patterns
-          .iter()
-          .filter_map(|p| Pattern::new(p).ok())
-          .collect()
-      })
-      .unwrap_or_default();
-
-    // 2. Configure base compression options
-    let compression_level = self.options.level.unwrap_or(1);
-    let base_options = SimpleFileOptions::default()
-      .compression_method(CompressionMethod::Deflated)
-      .compression_level(Some(compression_level as i64))
-      .large_file(true); // Enable Zip64
-
-    let walk = WalkDir::new(&self.source_dir);
-    let mut buffer = vec![0; 65536]; // Reusable 64KB read buffer
-    let mut file_count = 0;
-
-    for entry in walk.into_iter().filter_map(|e| e.ok()) {
+    let zip = zip::ZipWriter::new(buf_writer);
+
+    let entries = self.sources.entries(&self.filter, &self.options);
+
+    let (mut writer, file_count) = write_archive(
+      zip,
+      entries,
+      &self.options,
+      &self.rules,
+      Some(temp_path),
+      cancel,
+    )?;
+    writer
+      .flush()
+      .map_err(|e| Error::from_reason(format!("Zip finalization failed: {}", e)))?;
+    Ok(file_count)
+  }
+}
+
+/// Walks `source_dir` in order, skipping filtered out paths and the root itself.
+///
+/// Entry names are the source-relative paths behind `prefix`. Siblings are
+/// sorted by name in deterministic mode, instead of coming in the order the
+/// filesystem lists them. Excluded, ignored and junk directories are pruned,
+/// so their contents are never visited.
+pub(crate) fn walk_entries<'a>(
+  source_dir: &Path,
+  prefix: &str,
+  filter: &'a EntryFilter,
+  options: &ZipOptions,
+) -> impl Iterator<Item = Result<SourceEntry>> + 'a {
+  let symlinks = options.symlinks.unwrap_or(Symlinks::Follow);
+  let mut walk = WalkDir::new(source_dir).follow_links(symlinks == Symlinks::Follow);
+  if options.deterministic.unwrap_or(false) {
+    walk = walk.sort_by_file_name();
+  }
+  let mut ignore_stack = filter.ignore_stack(prefix);
+  let walk_root = source_dir.to_path_buf();
+  let source_dir = source_dir.to_path_buf();
+  let prefix = prefix.to_string();
+  walk
+    .into_iter()
+    .filter_entry(move |entry| {
+      // Unnamed entries are kept, so the error shows up below
+      let Some(name) = entry_name(&walk_root, entry.path()) else {
+        return true;
+      };
+      ignore_stack.allows(
+        entry.path(),
+        &name,
+        entry.depth(),
+        entry.file_type().is_dir(),
+      )
+    })
+    .filter_map(|e| e.ok())
+    .filter_map(move |entry| {
       let path = entry.path();
 
-      // 3. Calculate and normalize path
-      let name_path = path
-        .strip_prefix(&self.source_dir)
-        .map_err(|e| Error::from_reason(format!("Path resolution error: {}", e)))?;
-
-      let name_str = name_path
-        .to_str()
-        .ok_or(Error::from_reason("Path contains invalid characters"))?;
-
-      // 4. Filter files
-      let mut matched = false;
-      for pattern in &exclude_patterns {
-        if pattern.matches(name_str) {
-          matched = true;
-          break;
+      // Calculate and normalize path
+      let name_str = match path.strip_prefix(&source_dir) {
+        Ok(name_path) => name_path.to_str(),
+        Err(e) => {
+          return Some(Err(Error::from_reason(format!(
+            "Path resolution error: {}",
+            e
+          ))));
         }
-      }
-      if matched {
-        continue;
+      };
+      let Some(name_str) = name_str else {
+        return Some(Err(Error::from_reason("Path contains invalid characters")));
+      };
+
+      // The source directory itself
+      let is_file = entry.file_type().is_file();
+      if !is_file && name_str.is_empty() {
+        return None;
       }
 
       // Normalize path separator to / on Windows
       #[cfg(windows)]
-      let name = name_str.replace("\\", "/");
+      let name = format!("{}{}", prefix, name_str.replace("\\", "/"));
       #[cfg(not(windows))]
-      let name = name_str.to_string();
+      let name = format!("{}{}", prefix, name_str);
 
-      if path.is_file() {
-        // 5. Get file permissions
-        let mut options = base_options;
+      // Filter files
+      if !filter.is_selected(&name) {
+        return None;
+      }
 
-        #[cfg(unix)]
-        {
-          use std::os::unix::fs::PermissionsExt;
-          if let Ok(metadata) = std::fs::metadata(path) {
-            options = options.unix_permissions(metadata.permissions().mode());
-          }
-        }
+      // Only seen when not following links
+      if entry.file_type().is_symlink() {
+        return match symlinks {
+          Symlinks::Skip => None,
+          _ => Some(symlink_entry(path, name)),
+        };
+      }
 
-        zip
-          .start_file(name, options)
-          .map_err(|e| Error::from_reason(format!("Failed to write zip entry: {}", e)))?;
+      Some(Ok(SourceEntry::from_path(
+        path.to_path_buf(),
+        name,
+        is_file,
+      )))
+    })
+}
 
-        let mut f = File::open(path)
-          .map_err(|e| Error::from_reason(format!("Failed to read source file: {}", e)))?;
+/// Entry name of `path` in `source_dir` with `/` separators, `None` when it isn't valid UTF-8.
+fn entry_name(source_dir: &Path, path: &Path) -> Option<String> {
+  let name = path.strip_prefix(source_dir).ok()?.to_str()?;
+  #[cfg(windows)]
+  let name = name.replace("\\", "/");
+  #[cfg(not(windows))]
+  let name = name.to_string();
+  Some(name)
+}
 
-        // Stream copy
-        loop {
-          let count = f
-            .read(&mut buffer)
-            .map_err(|e| Error::from_reason(format!("File stream read interrupted: {}", e)))?;
-          if count == 0 {
-            break;
-          }
-          zip
-            .write_all(&buffer[..count])
-            .map_err(|e| Error::from_reason(format!("Failed to write data: {}", e)))?;
-        }
-        file_count += 1;
-      } else if !name.is_empty() {
-        // Add directory
-        #[cfg(unix)]
-        {
-          // Preserve directory permissions
-          let mut options = base_options;
-          use std::os::unix::fs::PermissionsExt;
-          if let Ok(metadata) = std::fs::metadata(path) {
-            options = options.unix_permissions(metadata.permissions().mode());
-          }
-          zip
-            .add_directory(name, options)
-            .map_err(|e| Error::from_reason(format!("Failed to add directory: {}", e)))?;
-        }
-        #[cfg(not(unix))]
-        {
-          zip
-            .add_directory(name, base_options)
-            .map_err(|e| Error::from_reason(format!("Failed to add directory: {}", e)))?;
-        }
+/// A symlink stored as is, with its target as the entry content.
+pub(crate) fn symlink_entry(path: &Path, name: String) -> Result<SourceEntry> {
+  let target = std::fs::read_link(path)
+    .map_err(|e| Error::from_reason(format!("Failed to read symlink: {}", e)))?;
+  let Some(target) = target.to_str() else {
+    return Err(Error::from_reason(
+      "Symlink target contains invalid characters",
+    ));
+  };
+
+  // Normalize path separator to / on Windows
+  #[cfg(windows)]
+  let target = target.replace("\\", "/");
+  #[cfg(not(windows))]
+  let target = target.to_string();
+
+  Ok(SourceEntry {
+    mode: Some(0o777),
+    link_target: Some(target),
+    ..SourceEntry::from_path(path.to_path_buf(), name, false)
+  })
+}
+
+/// Writes `entries` into `zip` and finishes it, returning the writer and the number of files.
+///
+/// Large entries compressed in parallel are spooled next to `spool_path`, or kept
+/// in memory when it is `None`.
+pub(crate) fn write_archive<W: Write + Seek>(
+  zip: ZipWriter<W>,
+  entries: impl Iterator<Item = Result<SourceEntry>>,
+  options: &ZipOptions,
+  rules: &EntryRules,
+  spool_path: Option<&Path>,
+  cancel: &CancelToken,
+) -> Result<(W, u32)> {
+  // Names were filtered without the prefix, its directories come first
+  let entries = rules
+    .prefix_directories()
+    .into_iter()
+    .map(Ok)
+    .chain(entries.map(|entry| entry.map(|entry| rules.with_prefix(entry))));
+
+  finish_archive(zip, entries, options, rules, spool_path, cancel)
+}
+
+/// Writes `entries`, already under the prefix, after what `zip` holds and finishes it.
+///
+/// On failure, the entry being written is dropped, so that the archive `zip`
+/// finalizes on drop only holds complete entries.
+pub(crate) fn finish_archive<W: Write + Seek>(
+  mut zip: ZipWriter<W>,
+  entries: impl Iterator<Item = Result<SourceEntry>>,
+  options: &ZipOptions,
+  rules: &EntryRules,
+  spool_path: Option<&Path>,
+  cancel: &CancelToken,
+) -> Result<(W, u32)> {
+  let mut progress = ProgressReporter::new(options.on_progress.as_ref(), None, None);
+
+  let result = match options.threads {
+    Some(threads) => parallel::write_entries(
+      &mut zip,
+      entries,
+      rules,
+      threads as usize,
+      spool_path,
+      cancel,
+      &mut progress,
+    ),
+    None => write_entries(&mut zip, entries, rules, cancel, &mut progress),
+  };
+  let file_count = match result {
+    Ok(file_count) => file_count,
+    Err(e) => {
+      if zip.is_writing_file() {
+        let _ = zip.abort_file();
       }
+      return Err(e);
     }
+  };
 
-    // 6. Finish writing
-    zip
-      .finish()
-      .map_err(|e| Error::from_reason(format!("Zip finalization failed: {}", e)))?;
+  // Finish writing
+  let writer = zip
+    .finish()
+    .map_err(|e| Error::from_reason(format!("Zip finalization failed: {}", e)))?;
+  progress.finish();
 
-    Ok(file_count)
+  Ok((writer, file_count))
+}
+
+/// Compresses entries one after another straight into `zip`.
+fn write_entries<W: Write + Seek>(
+  zip: &mut ZipWriter<W>,
+  entries: impl Iterator<Item = Result<SourceEntry>>,
+  rules: &EntryRules,
+  cancel: &CancelToken,
+  progress: &mut ProgressReporter,
+) -> Result<u32> {
+  let mut buffer = vec![0; 65536]; // Reusable 64KB read buffer
+  let mut file_count = 0;
+
+  for entry in entries {
+    cancel.check()?;
+    let entry = entry?;
+    progress.start_entry(&entry.name);
+
+    if entry.is_file {
+      add_file(zip, &entry, rules, &mut buffer, cancel, progress)?;
+      file_count += 1;
+    } else if let Some(target) = &entry.link_target {
+      let options = rules.directory_options(&entry);
+      add_symlink(zip, &entry.name, target, options)?;
+    } else {
+      let options = rules.directory_options(&entry);
+      add_directory(zip, entry.name, options)?;
+    }
+    progress.finish_entry();
+  }
+
+  Ok(file_count)
+}
+
+pub(crate) fn check_threads(threads: Option<u32>) -> Result<()> {
+  if threads == Some(0) {
+    return Err(Error::from_reason("Thread count must be at least 1"));
+  }
+  Ok(())
+}
+
+/// Applies the entry permissions and modification time on top of `base_options`.
+pub(crate) fn entry_options(
+  base_options: FullFileOptions<'static>,
+  entry: &SourceEntry,
+) -> FullFileOptions<'static> {
+  let mut options = base_options;
+  if let Some(mtime) = entry_mtime(entry) {
+    options = with_mtime(options, mtime);
+  }
+  match entry_mode(entry) {
+    Some(mode) => options.unix_permissions(mode),
+    None => options,
+  }
+}
+
+/// Sets the DOS timestamp and the extended timestamp (UT) extra field to `seconds` since the Unix epoch.
+///
+/// The DOS timestamp falls back to 1980-01-01 outside of its range.
+pub(crate) fn with_mtime(
+  options: FullFileOptions<'static>,
+  seconds: i64,
+) -> FullFileOptions<'static> {
+  let mut options =
+    options.last_modified_time(dos_time(seconds.saturating_mul(1000)).unwrap_or_default());
+  if let Some(data) = extended_timestamp(seconds) {
+    // Only fails past 64KB of extra data
+    let _ = options.add_extra_data(EXTENDED_TIMESTAMP_ID, data, false);
+  }
+  options
+}
+
+/// Modification time of `entry` in seconds since the Unix epoch, read from disk when not given.
+///
+/// Virtual entries without one get the current time from the zip writer.
+pub(crate) fn entry_mtime(entry: &SourceEntry) -> Option<i64> {
+  if entry.mtime.is_some() || entry.content.is_some() {
+    return entry.mtime;
+  }
+  // The link itself for preserved symlinks
+  let metadata = match entry.link_target {
+    Some(_) => std::fs::symlink_metadata(&entry.path),
+    None => std::fs::metadata(&entry.path),
+  };
+  metadata
+    .and_then(|metadata| metadata.modified())
+    .ok()
+    .map(unix_seconds)
+}
+
+/// Unix permissions of `entry`, read from disk when not given.
+pub(crate) fn entry_mode(entry: &SourceEntry) -> Option<u32> {
+  if entry.mode.is_some() {
+    return entry.mode;
+  }
+  #[cfg(unix)]
+  if entry.content.is_none() {
+    use std::os::unix::fs::PermissionsExt;
+    if let Ok(metadata) = std::fs::metadata(&entry.path) {
+      return Some(metadata.permissions().mode());
+    }
+  }
+  None
+}
+
+/// Adds a directory entry by merging a one-entry archive built in memory.
+///
+/// On a non-seekable stream, `ZipWriter::add_directory` flags the entry as having
+/// a data descriptor but never writes one, which `unzip` rejects. The merged header
+/// is the same one `add_directory` writes to a seekable output.
+pub(crate) fn add_directory<W: Write + Seek>(
+  zip: &mut ZipWriter<W>,
+  name: String,
+  options: FullFileOptions<'static>,
+) -> Result<()> {
+  let mut directory = ZipWriter::new(Cursor::new(Vec::new()));
+  directory
+    .add_directory(name, options)
+    .and_then(|_| directory.finish_into_readable())
+    .and_then(|archive| zip.merge_archive(archive))
+    .map_err(|e| Error::from_reason(format!("Failed to add directory: {}", e)))
+}
+
+/// Adds a symlink entry, stored with `target` as its content.
+pub(crate) fn add_symlink<W: Write + Seek>(
+  zip: &mut ZipWriter<W>,
+  name: &str,
+  target: &str,
+  options: FullFileOptions<'static>,
+) -> Result<()> {
+  zip
+    .add_symlink(name, target, options)
+    .map_err(|e| Error::from_reason(format!("Failed to add symlink: {}", e)))
+}
+
+/// Compresses a file entry straight into `zip`.
+pub(crate) fn add_file<W: Write + Seek>(
+  zip: &mut ZipWriter<W>,
+  entry: &SourceEntry,
+  rules: &EntryRules,
+  buffer: &mut [u8],
+  cancel: &CancelToken,
+  progress: &mut ProgressReporter,
+) -> Result<()> {
+  let options = rules.file_options(entry);
+  zip
+    .start_file(entry.name.as_str(), options)
+    .map_err(|e| Error::from_reason(format!("Failed to write zip entry: {}", e)))?;
+  copy_entry_data(entry, zip, buffer, cancel, |count| {
+    progress.add_bytes(count)
+  })
+}
+
+/// Streams the content of `entry` into the entry currently open in `writer`.
+pub(crate) fn copy_entry_data<W: Write>(
+  entry: &SourceEntry,
+  writer: &mut W,
+  buffer: &mut [u8],
+  cancel: &CancelToken,
+  mut on_chunk: impl FnMut(usize),
+) -> Result<()> {
+  let mut f = entry.open()?;
+
+  // Stream copy
+  loop {
+    let count = f
+      .read(buffer)
+      .map_err(|e| Error::from_reason(format!("File stream read interrupted: {}", e)))?;
+    if count == 0 {
+      break;
+    }
+    cancel.check()?;
+    writer
+      .write_all(&buffer[..count])
+      .map_err(|e| Error::from_reason(format!("Failed to write data: {}", e)))?;
+    on_chunk(count);
+  }
+  Ok(())
+}
+
+impl Task for CompressTask {
+  type Output = u32;
+  type JsValue = u32;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let cancel = self.options.signal.clone().unwrap_or_default();
+    self.compress(&cancel)
   }
 
   fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
     Ok(output)
   }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    error::reject(&env, err)
+  }
 }
 
-/// Compress a directory into a zip file.
+/// Compress a directory, or a list of files, directories and globs, into a zip file.
 ///
-/// Returns the number of files compressed.
+/// Returns the number of files compressed, the added and replaced ones when updating an existing archive.
 ///
 /// # Arguments
-/// * `source_dir` - Source directory path
+/// * `source` - Source directory path, or an array of `ZipSource`
 /// * `output_path` - Output zip file path
 /// * `options` - Compression options
-///   - `level`: Compression level (0-9, default: 1)
-///   - `exclude`: Array of glob patterns to exclude files
+///   - `method`: Compression method (default: deflate)
+///   - `level`: Compression level, range depends on `method` (deflate: 0-9, default: 1)
+///   - `exclude`: Array of glob patterns to exclude files, `!` negates and the last match wins
+///   - `include`: Array of glob patterns, only matching files are compressed
+///   - `respectGitignore`: Skip what `.gitignore` files ignore, and `.git`
+///   - `ignoreFiles`: Names of other ignore files read like `.gitignore`
+///   - `excludeJunk`: Skip OS metadata files like `.DS_Store` and `Thumbs.db`
+///   - `matchOptions`: How `include`, `exclude` and `overrides` globs match
+///   - `onProgress`: Callback receiving throttled progress updates
+///   - `signal`: AbortSignal that cancels the task and removes the partial zip file
+///   - `threads`: Number of worker threads compressing entries in parallel, same bytes as sequential compression
+///   - `autoStore`: Store already compressed file types without compression (default: true)
+///   - `storeExtensions`: Extensions treated as already compressed, replacing the built-in list
+///   - `detectIncompressible`: Also store files whose first block looks incompressible
+///   - `overrides`: Glob to method or `{ method, level }`, the first matching glob wins
+///   - `password`: Encrypts file entries with this password
+///   - `encryption`: Encryption used with `password` (default: aes256)
+///   - `symlinks`: Store symlinks as links (`preserve`), as what they point to (`follow`), or leave them out (`skip`) (default: follow)
+///   - `deterministic`: Sort entries and fix their mtime and permissions, for byte-identical archives
+///   - `prefix`: Directory every entry goes in, added after glob matching
+///   - `mode`: What to do with an existing archive, `create` overwrites it (default: create)
 #[napi(ts_return_type = "Promise<number>")]
 pub fn zip(
-  source_dir: String,
+  source: Either<String, Vec<ZipSource>>,
   output_path: String,
   options: Option<ZipOptions>,
 ) -> Result<AsyncTask<CompressTask>> {
-  let opts = options.unwrap_or(ZipOptions {
-    level: Some(1),
-    exclude: None,
-  });
-
-  let compression_level = opts.level.unwrap_or(1);
-  if !(0..=9).contains(&compression_level) {
-    return Err(Error::from_reason(format!(
-      "Compression level must be between 0 and 9 (current: {})",
-      compression_level
-    )));
-  }
+  let opts = options.unwrap_or_default();
+
+  let rules = EntryRules::new(&opts)?;
+  let filter = EntryFilter::new(&opts)?;
+  check_threads(opts.threads)?;
+  let sources = Sources::new(source)?;
 
   Ok(AsyncTask::new(CompressTask {
-    source_dir: PathBuf::from(source_dir),
+    sources,
     output_path: PathBuf::from(output_path),
     options: opts,
+    rules,
+    filter,
   }))
 }
 
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct UnzipOptions {
+  #[napi(ts_type = "(progress: Progress) => void")]
+  pub on_progress: Option<ProgressCallback>,
+  #[napi(ts_type = "AbortSignal")]
+  pub signal: Option<CancelToken>,
+  pub threads: Option<u32>,
+  pub password: Option<String>,
+  pub include: Option<Vec<String>>,
+  pub exclude: Option<Vec<String>>,
+  pub match_options: Option<MatchOptions>,
+  pub restore_mtimes: Option<bool>,
+}
+
 pub struct UncompressTask {
-  pub source_path: PathBuf,
+  pub(crate) source: ArchiveInput,
   pub output_dir: PathBuf,
+  pub options: UnzipOptions,
+  pub(crate) filter: NameFilter,
 }
 
-impl Task for UncompressTask {
-  type Output = ();
-  type JsValue = ();
+/// An archive entry that passed the Zip Slip check, planned for extraction.
+pub(crate) struct ExtractEntry {
+  pub index: usize,
+  pub name: String,
+  pub outpath: PathBuf,
+  pub is_dir: bool,
+  pub is_symlink: bool,
+  #[cfg_attr(not(unix), allow(dead_code))]
+  pub mode: Option<u32>,
+  /// Modification time to restore, in seconds since the Unix epoch
+  pub mtime: Option<i64>,
+}
 
-  fn compute(&mut self) -> Result<Self::Output> {
-    let file = File::open(&self.source_path)
-      .map_err(|e| Error::from_reason(format!("Failed to open zip file: {}", e)))?;
-    let mut archive = zip::ZipArchive::new(file)
-      .map_err(|e| Error::from_reason(format!("Failed to read zip archive: {}", e)))?;
+impl UncompressTask {
+  fn extract(&self, cancel: &CancelToken, created: &Mutex<CreatedPaths>) -> Result<()> {
+    let mut archive = self.source.open()?;
 
+    let restore_mtimes = self.options.restore_mtimes.unwrap_or(true);
+
+    // Plan entries from the central directory
+    let mut entries = Vec::with_capacity(archive.len());
+    let mut bytes_total: u64 = 0;
     for i in 0..archive.len() {
-      let mut file = archive
-        .by_index(i)
+      let file = archive
+        .by_index_raw(i)
         .map_err(|e| Error::from_reason(format!("Failed to read zip entry: {}", e)))?;
 
+      // Directories are matched without their trailing slash
+      let name = file.name().trim_end_matches('/');
+      if !self.filter.is_selected(name) {
+        continue;
+      }
+
+      method::check_supported(file.compression(), file.name())?;
+
       // Security check: Zip Slip
-      let outpath = match file.enclosed_name() {
-        Some(path) => self.output_dir.join(path),
-        None => continue,
-      };
+      if let Some(path) = file.enclosed_name() {
+        bytes_total = bytes_total.saturating_add(file.size());
+        entries.push(ExtractEntry {
+          index: i,
+          name: file.name().to_string(),
+          outpath: self.output_dir.join(path),
+          is_dir: file.name().ends_with('/'),
+          is_symlink: file.is_symlink(),
+          mode: file.unix_mode(),
+          mtime: archive_mtime(&file).filter(|_| restore_mtimes),
+        });
+      }
+    }
 
-      if file.name().ends_with('/') {
-        std::fs::create_dir_all(&outpath)
-          .map_err(|e| Error::from_reason(format!("Failed to create directory: {}", e)))?;
-      } else {
-        #[allow(clippy::collapsible_if)]
-        if let Some(p) = outpath.parent() {
-          if !p.exists() {
-            std::fs::create_dir_all(p).map_err(|e| {
+    let progress = Mutex::new(ProgressReporter::new(
+      self.options.on_progress.as_ref(),
+      Some(entries.len() as u32),
+      Some(bytes_total.min(i64::MAX as u64) as i64),
+    ));
+
+    // Create directories up front
+    {
+      let mut created = created.lock().unwrap();
+      let mut ensured: HashSet<&Path> = HashSet::new();
+      #[allow(clippy::collapsible_if)]
+      for entry in &entries {
+        cancel.check()?;
+        if entry.is_dir {
+          if ensured.insert(&entry.outpath) {
+            created
+              .create_dir_all(&entry.outpath)
+              .map_err(|e| Error::from_reason(format!("Failed to create directory: {}", e)))?;
+          }
+          progress.lock().unwrap().finish_entry();
+        } else if let Some(p) = entry.outpath.parent() {
+          if ensured.insert(p) {
+            created.create_dir_all(p).map_err(|e| {
               Error::from_reason(format!("Failed to create parent directory: {}", e))
             })?;
           }
         }
-        let mut outfile = File::create(&outpath)
-          .map_err(|e| Error::from_reason(format!("Failed to create output file: {}", e)))?;
-        std::io::copy(&mut file, &mut outfile)
-          .map_err(|e| Error::from_reason(format!("Failed to decompress file content: {}", e)))?;
       }
+    }
+
+    // Write file data
+    let files: Vec<&ExtractEntry> = entries
+      .iter()
+      .filter(|e| !e.is_dir && !e.is_symlink)
+      .collect();
+    match self.options.threads {
+      Some(threads) => parallel::extract_files(
+        &self.source,
+        &files,
+        threads as usize,
+        self.options.password.as_deref(),
+        cancel,
+        &progress,
+        created,
+      )?,
+      None => {
+        let password = self.options.password.as_deref();
+        let mut buffer = vec![0; 65536]; // Reusable 64KB read buffer
+        for entry in files {
+          cancel.check()?;
+          extract_file(
+            &mut archive,
+            entry,
+            password,
+            &mut buffer,
+            cancel,
+            &progress,
+            created,
+          )?;
+        }
+      }
+    }
+
+    // Create symlinks last, so no entry is written through one
+    for entry in entries.iter().filter(|e| e.is_symlink) {
+      cancel.check()?;
+      let mut target = String::new();
+      open_entry(
+        &mut archive,
+        entry.index,
+        &entry.name,
+        self.options.password.as_deref(),
+      )?
+      .read_to_string(&mut target)
+      .map_err(|e| Error::from_reason(format!("Failed to read symlink target: {}", e)))?;
+      if symlink::restore_symlink(&self.output_dir, &entry.outpath, &target)? {
+        created.lock().unwrap().record_file(&entry.outpath);
+      }
+      progress.lock().unwrap().finish_entry();
+    }
+
+    // Restore directory mtimes once nothing is added to them anymore
+    for entry in entries.iter().filter(|e| e.is_dir) {
+      if let Some(mtime) = entry.mtime {
+        set_dir_mtime(&entry.outpath, mtime)
+          .map_err(|e| Error::from_reason(format!("Failed to set modification time: {}", e)))?;
+      }
+    }
 
-      // Restore permissions (Unix only)
-      #[cfg(unix)]
-      {
-        use std::os::unix::fs::PermissionsExt;
-        if let Some(mode) = file.unix_mode() {
-          std::fs::set_permissions(&outpath, std::fs::Permissions::from_mode(mode))
+    // Restore permissions (Unix only) once all data is written
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      // Setting permissions on a symlink would change its target
+      for entry in entries.iter().filter(|e| !e.is_symlink) {
+        if let Some(mode) = entry.mode {
+          std::fs::set_permissions(&entry.outpath, std::fs::Permissions::from_mode(mode))
             .map_err(|e| Error::from_reason(format!("Failed to set file permissions: {}", e)))?;
         }
       }
     }
 
+    progress.lock().unwrap().finish();
+
     Ok(())
   }
+}
+
+/// A zip archive read from disk or from memory.
+pub(crate) enum ArchiveInput {
+  Path(PathBuf),
+  Buffer(Arc<[u8]>),
+}
+
+impl ArchiveInput {
+  /// Opens a new handle on the archive, workers each open their own.
+  pub fn open(&self) -> Result<ZipArchive<ArchiveReader>> {
+    let reader = match self {
+      ArchiveInput::Path(path) => ArchiveReader::File(
+        File::open(path)
+          .map_err(|e| Error::from_reason(format!("Failed to open zip file: {}", e)))?,
+      ),
+      ArchiveInput::Buffer(data) => ArchiveReader::Memory(Cursor::new(data.clone())),
+    };
+    ZipArchive::new(reader)
+      .map_err(|e| Error::from_reason(format!("Failed to read zip archive: {}", e)))
+  }
+}
+
+pub(crate) enum ArchiveReader {
+  File(File),
+  Memory(Cursor<Arc<[u8]>>),
+}
+
+impl Read for ArchiveReader {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    match self {
+      ArchiveReader::File(file) => file.read(buf),
+      ArchiveReader::Memory(cursor) => cursor.read(buf),
+    }
+  }
+}
+
+impl Seek for ArchiveReader {
+  fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+    match self {
+      ArchiveReader::File(file) => file.seek(pos),
+      ArchiveReader::Memory(cursor) => cursor.seek(pos),
+    }
+  }
+}
+
+pub(crate) fn open_archive(path: &Path) -> Result<ZipArchive<File>> {
+  let file =
+    File::open(path).map_err(|e| Error::from_reason(format!("Failed to open zip file: {}", e)))?;
+  ZipArchive::new(file)
+    .map_err(|e| Error::from_reason(format!("Failed to read zip archive: {}", e)))
+}
+
+/// Opens entry `index` for reading, decrypting it with `password` when given.
+pub(crate) fn open_entry<'a, R: Read + Seek>(
+  archive: &'a mut ZipArchive<R>,
+  index: usize,
+  name: &str,
+  password: Option<&str>,
+) -> Result<ZipFile<'a, R>> {
+  let file = match password {
+    Some(password) => archive.by_index_decrypt(index, password.as_bytes()),
+    None => archive.by_index(index),
+  };
+  file.map_err(|e| error::entry_error(e, name))
+}
+
+/// Decompresses a single file entry to its planned output path.
+pub(crate) fn extract_file<R: Read + Seek>(
+  archive: &mut ZipArchive<R>,
+  entry: &ExtractEntry,
+  password: Option<&str>,
+  buffer: &mut [u8],
+  cancel: &CancelToken,
+  progress: &Mutex<ProgressReporter>,
+  created: &Mutex<CreatedPaths>,
+) -> Result<()> {
+  let mut file = open_entry(archive, entry.index, &entry.name, password)?;
+  progress.lock().unwrap().start_entry(file.name());
+
+  let mut outfile = File::create(&entry.outpath)
+    .map_err(|e| Error::from_reason(format!("Failed to create output file: {}", e)))?;
+  created.lock().unwrap().record_file(&entry.outpath);
+
+  // Stream copy
+  loop {
+    let count = file
+      .read(buffer)
+      .map_err(|e| Error::from_reason(format!("Failed to decompress file content: {}", e)))?;
+    if count == 0 {
+      break;
+    }
+    cancel.check()?;
+    outfile
+      .write_all(&buffer[..count])
+      .map_err(|e| Error::from_reason(format!("Failed to write output file: {}", e)))?;
+    progress.lock().unwrap().add_bytes(count);
+  }
+
+  if let Some(mtime) = entry.mtime {
+    outfile
+      .set_modified(system_time(mtime))
+      .map_err(|e| Error::from_reason(format!("Failed to set modification time: {}", e)))?;
+  }
+
+  progress.lock().unwrap().finish_entry();
+  Ok(())
+}
+
+impl Task for UncompressTask {
+  type Output = ();
+  type JsValue = ();
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let cancel = self.options.signal.clone().unwrap_or_default();
+    let created = Mutex::new(CreatedPaths::new(self.options.signal.is_some()));
+    let result = self.extract(&cancel, &created);
+    if result.is_err() && cancel.is_cancelled() {
+      created.lock().unwrap().remove_all();
+    }
+    result
+  }
 
   fn resolve(&mut self, _env: Env, _output: Self::Output) -> Result<Self::JsValue> {
     Ok(())
   }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    error::reject(&env, err)
+  }
 }
 
 /// Decompress a zip file into a directory.
@@ -263,14 +950,40 @@ impl Task for UncompressTask {
 /// Automatically creates the output directory if it doesn't exist.
 /// Safely handles paths to prevent writing outside the target directory (Zip Slip protection).
 /// Restores file permissions on Unix systems.
+/// Restores symlinks whose target stays inside `output_dir` and skips the others.
+/// Restores modification times, from the extended timestamp field when an entry has one.
 ///
 /// # Arguments
 /// * `source_path` - Source zip file path
 /// * `output_dir` - Output directory path
+/// * `options` - Decompression options
+///   - `onProgress`: Callback receiving throttled progress updates
+///   - `signal`: AbortSignal that cancels the task and removes the files extracted so far
+///   - `threads`: Number of worker threads extracting entries in parallel
+///   - `password`: Password of encrypted entries, wrong passwords reject with code `InvalidPassword`
+///   - `include`: Array of glob patterns, only matching entries are extracted
+///   - `exclude`: Array of glob patterns to skip entries
+///   - `matchOptions`: How `include` and `exclude` globs match
+///   - `restoreMtimes`: Restore the modification times of files and directories (default: true)
 #[napi(ts_return_type = "Promise<void>")]
-pub fn unzip(source_path: String, output_dir: String) -> AsyncTask<UncompressTask> {
-  AsyncTask::new(UncompressTask {
-    source_path: PathBuf::from(source_path),
+pub fn unzip(
+  source_path: String,
+  output_dir: String,
+  options: Option<UnzipOptions>,
+) -> Result<AsyncTask<UncompressTask>> {
+  let opts = options.unwrap_or_default();
+
+  check_threads(opts.threads)?;
+  let filter = NameFilter::new(
+    opts.include.as_deref(),
+    opts.exclude.as_deref(),
+    opts.match_options,
+  )?;
+
+  Ok(AsyncTask::new(UncompressTask {
+    source: ArchiveInput::Path(PathBuf::from(source_path)),
     output_dir: PathBuf::from(output_dir),
-  })
+    options: opts,
+    filter,
+  }))
 }
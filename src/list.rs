@@ -0,0 +1,89 @@
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use std::path::PathBuf;
+
+use crate::method::{method_code, method_name};
+use crate::open_archive;
+use crate::symlink;
+use crate::time::archive_mtime;
+
+/// Metadata of an archive entry, as read from the central directory.
+#[napi(object, object_from_js = false)]
+pub struct EntryInfo {
+  /// Entry name, directories end with `/`
+  pub name: String,
+  /// Uncompressed size in bytes
+  pub size: i64,
+  /// Compressed size in bytes
+  pub compressed_size: i64,
+  /// Compression method, e.g. `deflate`, or `unknown (<code>)`
+  pub method: String,
+  pub crc32: u32,
+  /// Last modification time in milliseconds since the Unix epoch, from the extended
+  /// timestamp field when present, else the DOS timestamp read as UTC
+  pub mtime: Option<i64>,
+  /// Unix mode including the file type bits, when the archive records one
+  pub unix_mode: Option<u32>,
+  pub is_dir: bool,
+  pub is_symlink: bool,
+  pub encrypted: bool,
+  pub comment: String,
+}
+
+pub struct ListTask {
+  pub source_path: PathBuf,
+}
+
+impl Task for ListTask {
+  type Output = Vec<EntryInfo>;
+  type JsValue = Vec<EntryInfo>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let mut archive = open_archive(&self.source_path)?;
+
+    let mut entries = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+      let file = archive
+        .by_index_raw(i)
+        .map_err(|e| Error::from_reason(format!("Failed to read zip entry: {}", e)))?;
+
+      let code = method_code(file.compression());
+      let unix_mode = file.unix_mode();
+      entries.push(EntryInfo {
+        name: file.name().to_string(),
+        size: file.size().min(i64::MAX as u64) as i64,
+        compressed_size: file.compressed_size().min(i64::MAX as u64) as i64,
+        method: match method_name(code) {
+          Some(name) => name.to_string(),
+          None => format!("unknown ({})", code),
+        },
+        crc32: file.crc32(),
+        mtime: archive_mtime(&file).map(|seconds| seconds * 1000),
+        unix_mode,
+        is_dir: file.is_dir(),
+        is_symlink: unix_mode.is_some_and(symlink::is_symlink_mode),
+        encrypted: file.encrypted(),
+        comment: file.comment().to_string(),
+      });
+    }
+    Ok(entries)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+/// List the entries of a zip file without extracting it.
+///
+/// Returns one `EntryInfo` per entry, in archive order.
+///
+/// # Arguments
+/// * `source_path` - Source zip file path
+#[napi(ts_return_type = "Promise<Array<EntryInfo>>")]
+pub fn list(source_path: String) -> AsyncTask<ListTask> {
+  AsyncTask::new(ListTask {
+    source_path: PathBuf::from(source_path),
+  })
+}
@@ -0,0 +1,477 @@
+use flate2::bufread::DeflateDecoder;
+use napi::{Error, Result};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Cursor, Read, Write};
+use std::path::{Component, PathBuf};
+use zip::DateTime;
+use zip::read::read_zipfile_from_stream;
+
+use crate::cancel::CancelToken;
+use crate::method;
+use crate::time::{EXTENDED_TIMESTAMP_ID, parse_extended_timestamp, unix_millis};
+
+const LOCAL_HEADER: [u8; 4] = *b"PK\x03\x04";
+const CENTRAL_HEADER: [u8; 4] = *b"PK\x01\x02";
+const END_OF_CENTRAL: [u8; 4] = *b"PK\x05\x06";
+const ZIP64_END_OF_CENTRAL: [u8; 4] = *b"PK\x06\x06";
+const DATA_DESCRIPTOR: [u8; 4] = *b"PK\x07\x08";
+
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_HEADER_SIZE: usize = 46;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+const FLAG_ENCRYPTED: u16 = 1;
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+const FLAG_UTF8: u16 = 1 << 11;
+
+const METHOD_STORE: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+/// Host system of a central directory entry whose external attributes hold a unix mode
+const SYSTEM_UNIX: u16 = 3;
+
+const READ_SIZE: usize = 64 * 1024;
+
+/// An entry as described by its local file header.
+pub(crate) struct LocalEntry {
+  pub name: String,
+  /// Modification time in seconds since the Unix epoch
+  pub mtime: Option<i64>,
+  /// Raw header, replayed to the zip crate for entries with known sizes
+  header: Vec<u8>,
+  flags: u16,
+  method: u16,
+  /// Sizes in the data descriptor are 8 bytes wide
+  zip64: bool,
+}
+
+impl LocalEntry {
+  pub fn is_dir(&self) -> bool {
+    self.name.ends_with('/')
+  }
+
+  fn has_descriptor(&self) -> bool {
+    self.flags & FLAG_DATA_DESCRIPTOR != 0
+  }
+}
+
+/// Reads an archive front to back from its local headers, for sources that can't seek.
+///
+/// Entries whose sizes only follow their data, in a data descriptor, are supported
+/// for store and deflate, the methods streaming writers use.
+pub(crate) struct LocalReader<R> {
+  inner: R,
+  buf: Vec<u8>,
+  pos: usize,
+}
+
+impl<R: Read> LocalReader<R> {
+  pub fn new(inner: R) -> Self {
+    LocalReader {
+      inner,
+      buf: Vec::with_capacity(READ_SIZE),
+      pos: 0,
+    }
+  }
+
+  /// Appends the next read from `inner` to the buffer, returning the bytes read.
+  fn read_more(&mut self) -> io::Result<usize> {
+    self.buf.drain(..self.pos);
+    self.pos = 0;
+    let len = self.buf.len();
+    self.buf.resize(len + READ_SIZE, 0);
+    let result = self.inner.read(&mut self.buf[len..]);
+    self.buf.truncate(len + result.as_ref().map_or(0, |&n| n));
+    result
+  }
+
+  /// Returns up to `n` bytes without consuming them, fewer only at the end of the input.
+  fn peek(&mut self, n: usize) -> io::Result<&[u8]> {
+    while self.buf.len() - self.pos < n {
+      if self.read_more()? == 0 {
+        break;
+      }
+    }
+    let end = self.buf.len().min(self.pos + n);
+    Ok(&self.buf[self.pos..end])
+  }
+
+  fn peek_signature(&mut self) -> io::Result<Option<[u8; 4]>> {
+    Ok(self.peek(4)?.try_into().ok())
+  }
+
+  fn read_vec(&mut self, n: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; n];
+    self.read_exact(&mut bytes)?;
+    Ok(bytes)
+  }
+
+  /// Reads the next local header, or returns `None` once the central directory starts.
+  pub fn next_entry(&mut self) -> Result<Option<LocalEntry>> {
+    match self.peek_signature().map_err(stream_error)? {
+      Some(LOCAL_HEADER) => {}
+      Some(CENTRAL_HEADER | END_OF_CENTRAL | ZIP64_END_OF_CENTRAL) => return Ok(None),
+      Some(_) => {
+        return Err(Error::from_reason(
+          "Invalid local file header in zip stream",
+        ));
+      }
+      None => return Err(Error::from_reason("Unexpected end of zip stream")),
+    }
+
+    let mut header = self.read_vec(LOCAL_HEADER_SIZE).map_err(stream_error)?;
+    let flags = u16_at(&header, 6);
+    let method = u16_at(&header, 8);
+    let name_len = u16_at(&header, 26) as usize;
+    let extra_len = u16_at(&header, 28) as usize;
+    let name_and_extra = self.read_vec(name_len + extra_len).map_err(stream_error)?;
+    header.extend_from_slice(&name_and_extra);
+
+    let (raw_name, extra) = name_and_extra.split_at(name_len);
+    let name = if flags & FLAG_UTF8 != 0 {
+      String::from_utf8_lossy(raw_name).into_owned()
+    } else {
+      // Legacy names are CP437, which shares the ASCII range nearly all of them stay in
+      raw_name.iter().map(|&b| b as char).collect()
+    };
+    if flags & FLAG_ENCRYPTED != 0 {
+      return Err(Error::from_reason(format!(
+        "Entry '{}' is encrypted, which streaming extraction doesn't support",
+        name
+      )));
+    }
+
+    // Same preference as `archive_mtime`: the extended timestamp, then the DOS one
+    let mtime = extra_fields(extra)
+      .find(|&(id, _)| id == EXTENDED_TIMESTAMP_ID)
+      .and_then(|(_, data)| parse_extended_timestamp(data))
+      .or_else(|| {
+        let time = DateTime::try_from_msdos(u16_at(&header, 12), u16_at(&header, 10)).ok()?;
+        Some(unix_millis(time) / 1000)
+      });
+
+    Ok(Some(LocalEntry {
+      zip64: extra_fields(extra).any(|(id, _)| id == ZIP64_EXTRA_ID),
+      mtime,
+      name,
+      header,
+      flags,
+      method,
+    }))
+  }
+
+  /// Decompresses the data of `entry` into `writer`, checking its CRC32 and size.
+  pub fn copy_entry<W: Write>(
+    &mut self,
+    entry: &LocalEntry,
+    writer: &mut W,
+    buffer: &mut [u8],
+    cancel: &CancelToken,
+    on_chunk: impl FnMut(usize),
+  ) -> Result<()> {
+    if !entry.has_descriptor() {
+      // Sizes are known up front, so the zip crate can decode any method it supports
+      let mut source = Cursor::new(entry.header.as_slice()).chain(&mut *self);
+      let file = read_zipfile_from_stream(&mut source)
+        .map_err(|e| Error::from_reason(format!("Failed to read zip entry: {}", e)))?
+        .ok_or_else(|| Error::from_reason("Invalid local file header in zip stream"))?;
+      method::check_supported(file.compression(), &entry.name)?;
+      return copy_data(file, writer, buffer, cancel, on_chunk);
+    }
+
+    let expected = match entry.method {
+      METHOD_STORE => {
+        let mut data = StoredData::new(self, entry.zip64);
+        copy_data(&mut data, writer, buffer, cancel, on_chunk)?;
+        // The descriptor that ended the data already matched it
+        return Ok(());
+      }
+      METHOD_DEFLATE => {
+        let mut data = Checksummed::new(DeflateDecoder::new(&mut *self));
+        copy_data(&mut data, writer, buffer, cancel, on_chunk)?;
+        let compressed_size = data.inner.total_in();
+        (data.crc32(), compressed_size, data.size)
+      }
+      method => {
+        return Err(Error::from_reason(format!(
+          "Entry '{}' uses compression method {} with a data descriptor, only store and deflate can be streamed",
+          entry.name, method
+        )));
+      }
+    };
+
+    let descriptor = self.read_descriptor(entry.zip64).map_err(stream_error)?;
+    if descriptor != expected {
+      return Err(Error::from_reason(format!(
+        "Failed to decompress file content: entry '{}' doesn't match its data descriptor",
+        entry.name
+      )));
+    }
+    Ok(())
+  }
+
+  /// Reads a data descriptor as `(crc32, compressed size, size)`.
+  fn read_descriptor(&mut self, zip64: bool) -> io::Result<(u32, u64, u64)> {
+    // The signature is optional
+    if self.peek_signature()? == Some(DATA_DESCRIPTOR) {
+      self.consume(4);
+    }
+    let descriptor = self.read_vec(descriptor_len(zip64))?;
+    Ok(parse_descriptor(&descriptor, zip64))
+  }
+
+  /// Reads the unix modes recorded in the central directory, by entry name.
+  ///
+  /// Local headers have no room for them, so they are only known once every
+  /// entry has been read. Reads the rest of the stream.
+  pub fn central_modes(&mut self) -> Result<HashMap<String, u32>> {
+    let mut modes = HashMap::new();
+    while self.peek_signature().map_err(stream_error)? == Some(CENTRAL_HEADER) {
+      let header = self.read_vec(CENTRAL_HEADER_SIZE).map_err(stream_error)?;
+      let system = u16_at(&header, 4) >> 8;
+      let name_len = u16_at(&header, 28) as usize;
+      let skip_len = u16_at(&header, 30) as u64 + u16_at(&header, 32) as u64;
+      let external_attributes = u32_at(&header, 38);
+      let raw_name = self.read_vec(name_len).map_err(stream_error)?;
+      io::copy(&mut (&mut *self).take(skip_len), &mut io::sink()).map_err(stream_error)?;
+
+      let mode = external_attributes >> 16;
+      if system == SYSTEM_UNIX && mode != 0 {
+        let name = if u16_at(&header, 8) & FLAG_UTF8 != 0 {
+          String::from_utf8_lossy(&raw_name).into_owned()
+        } else {
+          raw_name.iter().map(|&b| b as char).collect()
+        };
+        modes.insert(name, mode);
+      }
+    }
+
+    // Drain the end of central directory records, so the whole input is consumed
+    io::copy(self, &mut io::sink()).map_err(stream_error)?;
+    Ok(modes)
+  }
+}
+
+impl<R: Read> Read for LocalReader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let available = self.fill_buf()?;
+    let len = available.len().min(buf.len());
+    buf[..len].copy_from_slice(&available[..len]);
+    self.consume(len);
+    Ok(len)
+  }
+}
+
+impl<R: Read> BufRead for LocalReader<R> {
+  fn fill_buf(&mut self) -> io::Result<&[u8]> {
+    if self.pos == self.buf.len() {
+      self.read_more()?;
+    }
+    Ok(&self.buf[self.pos..])
+  }
+
+  fn consume(&mut self, amount: usize) {
+    self.pos = (self.pos + amount).min(self.buf.len());
+  }
+}
+
+/// Tracks the CRC32 and size of the data read through it.
+struct Checksummed<R> {
+  inner: R,
+  hasher: crc32fast::Hasher,
+  size: u64,
+}
+
+impl<R> Checksummed<R> {
+  fn new(inner: R) -> Self {
+    Checksummed {
+      inner,
+      hasher: crc32fast::Hasher::new(),
+      size: 0,
+    }
+  }
+
+  fn crc32(&self) -> u32 {
+    self.hasher.clone().finalize()
+  }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let count = self.inner.read(buf)?;
+    self.hasher.update(&buf[..count]);
+    self.size += count as u64;
+    Ok(count)
+  }
+}
+
+/// Stored data followed by a data descriptor.
+///
+/// Nothing marks the end of stored data, so it ends at the first descriptor
+/// signature followed by the CRC32 and sizes of the data read so far.
+struct StoredData<'a, R> {
+  input: &'a mut LocalReader<R>,
+  zip64: bool,
+  hasher: crc32fast::Hasher,
+  size: u64,
+  done: bool,
+}
+
+impl<'a, R: Read> StoredData<'a, R> {
+  fn new(input: &'a mut LocalReader<R>, zip64: bool) -> Self {
+    StoredData {
+      input,
+      zip64,
+      hasher: crc32fast::Hasher::new(),
+      size: 0,
+      done: false,
+    }
+  }
+
+  /// Consumes the descriptor at the current position if it matches the data.
+  fn end_at_descriptor(&mut self) -> io::Result<bool> {
+    let len = 4 + descriptor_len(self.zip64);
+    let candidate = self.input.peek(len)?;
+    if candidate.len() < len {
+      return Ok(false);
+    }
+    let expected = (self.hasher.clone().finalize(), self.size, self.size);
+    if parse_descriptor(&candidate[4..], self.zip64) != expected {
+      return Ok(false);
+    }
+    self.input.consume(len);
+    Ok(true)
+  }
+}
+
+impl<R: Read> Read for StoredData<'_, R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.done || buf.is_empty() {
+      return Ok(0);
+    }
+    loop {
+      let available = self.input.fill_buf()?;
+      if available.is_empty() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+      }
+      let len = match find(available, &DATA_DESCRIPTOR) {
+        Some(0) => {
+          if self.end_at_descriptor()? {
+            self.done = true;
+            return Ok(0);
+          }
+          // Just data that looks like a signature
+          1
+        }
+        Some(index) => index,
+        // Hold back a possible signature split across reads
+        None if available.len() > 3 => available.len() - 3,
+        None => {
+          if self.input.peek(4)?.len() < 4 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+          }
+          continue;
+        }
+      };
+
+      let available = self.input.fill_buf()?;
+      let len = len.min(buf.len());
+      buf[..len].copy_from_slice(&available[..len]);
+      self.hasher.update(&buf[..len]);
+      self.size += len as u64;
+      self.input.consume(len);
+      return Ok(len);
+    }
+  }
+}
+
+fn copy_data<R: Read, W: Write>(
+  mut reader: R,
+  writer: &mut W,
+  buffer: &mut [u8],
+  cancel: &CancelToken,
+  mut on_chunk: impl FnMut(usize),
+) -> Result<()> {
+  loop {
+    let count = reader
+      .read(buffer)
+      .map_err(|e| Error::from_reason(format!("Failed to decompress file content: {}", e)))?;
+    if count == 0 {
+      return Ok(());
+    }
+    cancel.check()?;
+    writer
+      .write_all(&buffer[..count])
+      .map_err(|e| Error::from_reason(format!("Failed to write output file: {}", e)))?;
+    on_chunk(count);
+  }
+}
+
+/// Same rules as `ZipFile::enclosed_name`: no absolute paths, and no `..` climbing out of the root.
+pub(crate) fn enclosed_name(name: &str) -> Option<PathBuf> {
+  if name.contains('\0') {
+    return None;
+  }
+  let path = PathBuf::from(name);
+  let mut depth = 0usize;
+  for component in path.components() {
+    match component {
+      Component::Prefix(_) | Component::RootDir => return None,
+      Component::ParentDir => depth = depth.checked_sub(1)?,
+      Component::Normal(_) => depth += 1,
+      Component::CurDir => {}
+    }
+  }
+  Some(path)
+}
+
+fn stream_error(e: io::Error) -> Error {
+  match e.kind() {
+    io::ErrorKind::UnexpectedEof => Error::from_reason("Unexpected end of zip stream"),
+    _ => Error::from_reason(format!("Failed to read zip stream: {}", e)),
+  }
+}
+
+fn descriptor_len(zip64: bool) -> usize {
+  if zip64 { 20 } else { 12 }
+}
+
+/// Parses a data descriptor without its signature.
+fn parse_descriptor(bytes: &[u8], zip64: bool) -> (u32, u64, u64) {
+  let crc32 = u32_at(bytes, 0);
+  if zip64 {
+    (crc32, u64_at(bytes, 4), u64_at(bytes, 12))
+  } else {
+    (crc32, u32_at(bytes, 4) as u64, u32_at(bytes, 8) as u64)
+  }
+}
+
+/// Ids and data of the fields in an extra field block.
+fn extra_fields(mut extra: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+  std::iter::from_fn(move || {
+    if extra.len() < 4 {
+      return None;
+    }
+    let id = u16_at(extra, 0);
+    let len = (u16_at(extra, 2) as usize).min(extra.len() - 4);
+    let data = &extra[4..4 + len];
+    extra = &extra[4 + len..];
+    Some((id, data))
+  })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+  u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+  u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+  u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
@@ -0,0 +1,187 @@
+use indexmap::IndexMap;
+use indexmap::map::Entry;
+use napi::bindgen_prelude::{AsyncTask, Either};
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use zip::ZipWriter;
+
+use crate::edit::{copy_raw, write_through_temp};
+use crate::open_archive;
+use crate::rules::archive_prefix;
+
+/// What `mergeArchives()` does with a file entry several inputs have.
+///
+/// `error` rejects, `first` keeps the entry of the earliest input and `last`
+/// the one of the latest input.
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnConflict {
+  Error,
+  First,
+  Last,
+}
+
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct MergeOptions {
+  pub on_conflict: Option<OnConflict>,
+  /// `true` puts the entries of each input under its file name without extension,
+  /// an array gives the directory of each input
+  pub prefix_each: Option<Either<bool, Vec<String>>>,
+}
+
+pub struct MergeTask {
+  pub inputs: Vec<PathBuf>,
+  pub output_path: PathBuf,
+  on_conflict: OnConflict,
+  /// Directory the entries of each input go in, empty or ending with `/`
+  prefixes: Vec<String>,
+}
+
+impl MergeTask {
+  /// Input and entry index of every output entry, in output order.
+  ///
+  /// Entries keep the position of the first input having them. Directories
+  /// several inputs have are stored once.
+  fn plan(&self) -> Result<IndexMap<String, (usize, usize)>> {
+    let mut plan: IndexMap<String, (usize, usize)> = IndexMap::new();
+    for (input, path) in self.inputs.iter().enumerate() {
+      let archive = open_archive(path)?;
+      for (index, name) in archive.file_names().enumerate() {
+        match plan.entry(format!("{}{}", self.prefixes[input], name)) {
+          Entry::Vacant(vacant) => {
+            vacant.insert((input, index));
+          }
+          Entry::Occupied(occupied) if occupied.key().ends_with('/') => {}
+          Entry::Occupied(mut occupied) => match self.on_conflict {
+            OnConflict::Error => {
+              return Err(Error::from_reason(format!(
+                "Entry '{}' is in both '{}' and '{}'",
+                occupied.key(),
+                self.inputs[occupied.get().0].display(),
+                path.display()
+              )));
+            }
+            OnConflict::First => {}
+            OnConflict::Last => {
+              occupied.insert((input, index));
+            }
+          },
+        }
+      }
+    }
+    Ok(plan)
+  }
+
+  fn merge(&self) -> Result<u32> {
+    let plan = self.plan()?;
+    let mut archives = self
+      .inputs
+      .iter()
+      .map(|path| open_archive(path))
+      .collect::<Result<Vec<_>>>()?;
+
+    write_through_temp(&self.output_path, |temp_path| {
+      let file = File::create(temp_path)
+        .map_err(|e| Error::from_reason(format!("Failed to create zip file: {}", e)))?;
+      let mut zip = ZipWriter::new(BufWriter::with_capacity(65536, file));
+      for (name, &(input, index)) in &plan {
+        copy_raw(&mut archives[input], index, &mut zip, Some(name))?;
+      }
+      zip
+        .finish()
+        .and_then(|mut w| w.flush().map_err(Into::into))
+        .map_err(|e| Error::from_reason(format!("Zip finalization failed: {}", e)))?;
+      Ok(plan.len() as u32)
+    })
+  }
+}
+
+impl Task for MergeTask {
+  type Output = u32;
+  type JsValue = u32;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    // Writing the output would truncate an input it overwrites
+    let output = self.output_path.canonicalize().ok();
+    if let Some(input) = self
+      .inputs
+      .iter()
+      .find(|input| output.is_some() && input.canonicalize().ok() == output)
+    {
+      return Err(Error::from_reason(format!(
+        "Output '{}' is also an input",
+        input.display()
+      )));
+    }
+
+    self.merge()
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+/// Directory every input goes in, from the `prefixEach` option.
+fn input_prefixes(
+  inputs: &[PathBuf],
+  prefix_each: Option<Either<bool, Vec<String>>>,
+) -> Result<Vec<String>> {
+  match prefix_each {
+    None | Some(Either::A(false)) => Ok(vec![String::new(); inputs.len()]),
+    Some(Either::A(true)) => inputs
+      .iter()
+      .map(
+        |input| match input.file_stem().and_then(|stem| stem.to_str()) {
+          Some(stem) => archive_prefix(stem),
+          None => Err(Error::from_reason(format!(
+            "Input '{}' has no file name",
+            input.display()
+          ))),
+        },
+      )
+      .collect(),
+    Some(Either::B(prefixes)) if prefixes.len() != inputs.len() => {
+      Err(Error::from_reason(format!(
+        "prefixEach has {} prefixes for {} inputs",
+        prefixes.len(),
+        inputs.len()
+      )))
+    }
+    Some(Either::B(prefixes)) => prefixes
+      .iter()
+      .map(|prefix| archive_prefix(prefix))
+      .collect(),
+  }
+}
+
+/// Merge zip files into a new one, copying their entries without recompression.
+///
+/// Returns the number of entries in the merged archive.
+///
+/// # Arguments
+/// * `inputs` - Zip file paths, in the order their entries are written
+/// * `output_path` - Output zip file path
+/// * `options` - Merge options
+///   - `onConflict`: What to do with a file several inputs have (default: error)
+///   - `prefixEach`: Put the entries of each input under its file name without extension (`true`), or under the given directories
+#[napi(ts_return_type = "Promise<number>")]
+pub fn merge_archives(
+  inputs: Vec<String>,
+  output_path: String,
+  options: Option<MergeOptions>,
+) -> Result<AsyncTask<MergeTask>> {
+  let options = options.unwrap_or_default();
+  let inputs: Vec<PathBuf> = inputs.into_iter().map(PathBuf::from).collect();
+  let prefixes = input_prefixes(&inputs, options.prefix_each)?;
+  Ok(AsyncTask::new(MergeTask {
+    inputs,
+    output_path: PathBuf::from(output_path),
+    on_conflict: options.on_conflict.unwrap_or(OnConflict::Error),
+    prefixes,
+  }))
+}
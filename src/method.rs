@@ -0,0 +1,145 @@
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::ops::RangeInclusive;
+use zip::CompressionMethod;
+use zip::write::FullFileOptions;
+
+/// Compression method used for new entries.
+///
+/// `bzip2`, `zstd` and `xz` are only available when the matching cargo feature is enabled.
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+  Store,
+  Deflate,
+  Bzip2,
+  Zstd,
+  Xz,
+}
+
+impl Method {
+  pub fn name(self) -> &'static str {
+    match self {
+      Method::Store => "store",
+      Method::Deflate => "deflate",
+      Method::Bzip2 => "bzip2",
+      Method::Zstd => "zstd",
+      Method::Xz => "xz",
+    }
+  }
+
+  fn is_available(self) -> bool {
+    match self {
+      Method::Store | Method::Deflate => true,
+      Method::Bzip2 => cfg!(feature = "bzip2"),
+      Method::Zstd => cfg!(feature = "zstd"),
+      Method::Xz => cfg!(feature = "xz"),
+    }
+  }
+
+  /// Accepted `level` values.
+  fn level_range(self) -> RangeInclusive<i32> {
+    match self {
+      // Ignored, but checked like the levels before methods could be chosen
+      Method::Store => 0..=9,
+      // 0 stores entries as is
+      Method::Deflate => 0..=9,
+      Method::Bzip2 => 1..=9,
+      Method::Zstd => 1..=22,
+      Method::Xz => 0..=9,
+    }
+  }
+
+  /// Level used when none is given; `None` keeps the zip crate default.
+  fn default_level(self) -> Option<i32> {
+    match self {
+      Method::Deflate => Some(1),
+      _ => None,
+    }
+  }
+
+  /// Checks that the method is compiled in and that `level` is in its range.
+  pub fn validate(self, level: Option<i32>) -> Result<()> {
+    if !self.is_available() {
+      return Err(Error::from_reason(format!(
+        "Compression method '{}' is not available in this build",
+        self.name()
+      )));
+    }
+    let range = self.level_range();
+    match level {
+      Some(level) if !range.contains(&level) => Err(Error::from_reason(format!(
+        "Compression level for {} must be between {} and {} (current: {})",
+        self.name(),
+        range.start(),
+        range.end(),
+        level
+      ))),
+      _ => Ok(()),
+    }
+  }
+
+  /// Base entry options for this method and level.
+  pub fn file_options(self, level: Option<i32>) -> FullFileOptions<'static> {
+    self.apply(FullFileOptions::default(), level)
+  }
+
+  /// Switches `options` to this method and level.
+  pub fn apply(
+    self,
+    options: FullFileOptions<'static>,
+    level: Option<i32>,
+  ) -> FullFileOptions<'static> {
+    let level = level.or(self.default_level());
+    let (method, level) = match self {
+      Method::Store => (CompressionMethod::Stored, None),
+      Method::Deflate if level == Some(0) => (CompressionMethod::Stored, None),
+      Method::Deflate => (CompressionMethod::Deflated, level),
+      Method::Bzip2 => (CompressionMethod::BZIP2, level),
+      Method::Zstd => (CompressionMethod::ZSTD, level),
+      Method::Xz => (CompressionMethod::XZ, level),
+    };
+    options
+      .compression_method(method)
+      .compression_level(level.map(i64::from))
+  }
+}
+
+/// Name of a zip compression method code, as reported by `list()`.
+pub fn method_name(code: u16) -> Option<&'static str> {
+  Some(match code {
+    0 => "store",
+    8 => "deflate",
+    9 => "deflate64",
+    12 => "bzip2",
+    14 => "lzma",
+    93 => "zstd",
+    95 => "xz",
+    98 => "ppmd",
+    99 => "aes",
+    _ => return None,
+  })
+}
+
+/// Compression method code as stored in the archive.
+pub fn method_code(method: CompressionMethod) -> u16 {
+  #[allow(deprecated)]
+  method.to_u16()
+}
+
+/// Rejects entries whose compression method was not compiled into this build.
+pub fn check_supported(method: CompressionMethod, name: &str) -> Result<()> {
+  #[allow(deprecated)]
+  if let CompressionMethod::Unsupported(code) = method {
+    let hint = match method_name(code) {
+      Some("aes") => " (aes, enable the `aes-crypto` feature)".to_string(),
+      Some(method) => format!(" ({}, enable the `{}` feature)", method, method),
+      None => String::new(),
+    };
+    return Err(Error::from_reason(format!(
+      "Entry '{}' uses unsupported compression method {}{}",
+      name, code, hint
+    )));
+  }
+  Ok(())
+}
@@ -0,0 +1,274 @@
+use napi::{Error, Result};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, mpsc};
+use zip::write::FullFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::cancel::{CancelToken, CreatedPaths};
+use crate::progress::ProgressReporter;
+use crate::rules::EntryRules;
+use crate::{
+  ArchiveInput, ExtractEntry, SourceEntry, add_directory, add_file, add_symlink, copy_entry_data,
+  extract_file,
+};
+
+/// Compressed entries larger than this are spooled to a temp file instead of memory
+const MEMORY_SPOOL_LIMIT: u64 = 8 * 1024 * 1024;
+
+/// Max number of entries in flight per worker thread
+const ENTRIES_PER_THREAD: usize = 4;
+
+/// A single compressed entry, stored as a one-file zip archive.
+enum Spool {
+  Memory(Vec<u8>),
+  File(TempSpool),
+}
+
+/// Temp spool file next to the output zip, removed on drop.
+struct TempSpool(PathBuf);
+
+impl Drop for TempSpool {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_file(&self.0);
+  }
+}
+
+/// Compresses file entries on a pool of `threads` workers and stitches them
+/// into `zip` in walk order by merging their spools.
+///
+/// Every entry goes through a spool, except the ones needing Zip64 fields which
+/// are compressed in turn on this thread, so the output is the same as sequential
+/// compression for any thread count. Large spools are written next to
+/// `output_path`, or kept in memory without one.
+pub fn write_entries<W: Write + Seek>(
+  zip: &mut ZipWriter<W>,
+  mut entries: impl Iterator<Item = Result<SourceEntry>>,
+  rules: &EntryRules,
+  threads: usize,
+  output_path: Option<&Path>,
+  cancel: &CancelToken,
+  progress: &mut ProgressReporter,
+) -> Result<u32> {
+  let window = threads * ENTRIES_PER_THREAD;
+  let (job_tx, job_rx) = mpsc::sync_channel::<(usize, SourceEntry)>(window);
+  let job_rx = Mutex::new(job_rx);
+  let (result_tx, result_rx) = mpsc::channel::<(usize, Result<Spool>)>();
+
+  std::thread::scope(|scope| {
+    for _ in 0..threads {
+      let job_rx = &job_rx;
+      let result_tx = result_tx.clone();
+      scope.spawn(move || {
+        let mut buffer = vec![0; 65536]; // Reusable 64KB read buffer
+        loop {
+          // Release the lock before compressing
+          let job = job_rx.lock().unwrap().recv();
+          let Ok((index, entry)) = job else {
+            break;
+          };
+          let spool = compress_entry(&entry, rules, index, output_path, &mut buffer, cancel);
+          if result_tx.send((index, spool)).is_err() {
+            break;
+          }
+        }
+      });
+    }
+    drop(result_tx);
+
+    // Dropping `job_tx` when this returns stops the workers, also on error
+    let job_tx = job_tx;
+    let mut queue: VecDeque<(usize, SourceEntry)> = VecDeque::with_capacity(window);
+    let mut done: HashMap<usize, Spool> = HashMap::new();
+    let mut next_index = 0;
+    let mut file_count = 0;
+    // Reusable 64KB read buffer for the entries compressed on this thread
+    let mut buffer = vec![0; 65536];
+
+    loop {
+      cancel.check()?;
+
+      // Keep the pool busy while preserving the walk order for writing
+      while queue.len() < window {
+        let Some(entry) = entries.next() else {
+          break;
+        };
+        let entry = entry?;
+        if entry.is_file && !entry.is_large() {
+          job_tx
+            .send((next_index, entry.clone()))
+            .map_err(|_| Error::from_reason("Compression worker exited unexpectedly"))?;
+        }
+        queue.push_back((next_index, entry));
+        next_index += 1;
+      }
+
+      let Some((index, entry)) = queue.pop_front() else {
+        break;
+      };
+      progress.start_entry(&entry.name);
+
+      if entry.is_file && entry.is_large() {
+        // Merging a spool would drop the Zip64 fields its sizes need
+        add_file(zip, &entry, rules, &mut buffer, cancel, progress)?;
+        file_count += 1;
+      } else if entry.is_file {
+        let spool = loop {
+          if let Some(spool) = done.remove(&index) {
+            break spool;
+          }
+          let (finished, spool) = result_rx
+            .recv()
+            .map_err(|_| Error::from_reason("Compression worker exited unexpectedly"))?;
+          done.insert(finished, spool?);
+        };
+        let size = write_spool(zip, spool)?;
+        progress.add_bytes(size as usize);
+        file_count += 1;
+      } else if let Some(target) = &entry.link_target {
+        let options = rules.directory_options(&entry);
+        add_symlink(zip, &entry.name, target, options)?;
+      } else {
+        let options = rules.directory_options(&entry);
+        add_directory(zip, entry.name, options)?;
+      }
+      progress.finish_entry();
+    }
+
+    Ok(file_count)
+  })
+}
+
+fn compress_entry(
+  entry: &SourceEntry,
+  rules: &EntryRules,
+  index: usize,
+  output_path: Option<&Path>,
+  buffer: &mut [u8],
+  cancel: &CancelToken,
+) -> Result<Spool> {
+  let options = rules.file_options(entry);
+  let output_path = match output_path {
+    Some(output_path) if entry.size()? > MEMORY_SPOOL_LIMIT => output_path,
+    _ => {
+      let mut spool = ZipWriter::new(Cursor::new(Vec::new()));
+      write_spool_entry(&mut spool, entry, options, buffer, cancel)?;
+      let cursor = spool
+        .finish()
+        .map_err(|e| Error::from_reason(format!("Zip finalization failed: {}", e)))?;
+      return Ok(Spool::Memory(cursor.into_inner()));
+    }
+  };
+
+  let mut spool_path = OsString::from(output_path);
+  spool_path.push(format!(".{}.spool", index));
+  let temp = TempSpool(PathBuf::from(spool_path));
+  let file = File::create(&temp.0)
+    .map_err(|e| Error::from_reason(format!("Failed to create spool file: {}", e)))?;
+  let mut spool = ZipWriter::new(BufWriter::with_capacity(65536, file));
+  write_spool_entry(&mut spool, entry, options, buffer, cancel)?;
+  spool
+    .finish()
+    .and_then(|mut w| w.flush().map_err(Into::into))
+    .map_err(|e| Error::from_reason(format!("Zip finalization failed: {}", e)))?;
+  Ok(Spool::File(temp))
+}
+
+fn write_spool_entry<W: Write + Seek>(
+  spool: &mut ZipWriter<W>,
+  entry: &SourceEntry,
+  options: FullFileOptions<'_>,
+  buffer: &mut [u8],
+  cancel: &CancelToken,
+) -> Result<()> {
+  spool
+    .start_file(entry.name.as_str(), options)
+    .map_err(|e| Error::from_reason(format!("Failed to write zip entry: {}", e)))?;
+  copy_entry_data(entry, spool, buffer, cancel, |_| {})
+}
+
+/// Copies the single spooled entry into `zip` without recompressing it, returning its uncompressed size.
+fn write_spool<W: Write + Seek>(zip: &mut ZipWriter<W>, spool: Spool) -> Result<u64> {
+  match spool {
+    Spool::Memory(bytes) => merge_spool(zip, Cursor::new(bytes)),
+    Spool::File(temp) => {
+      let file = File::open(&temp.0)
+        .map_err(|e| Error::from_reason(format!("Failed to read spool file: {}", e)))?;
+      merge_spool(zip, file)
+    }
+  }
+}
+
+fn merge_spool<W: Write + Seek, R: Read + Seek>(zip: &mut ZipWriter<W>, reader: R) -> Result<u64> {
+  let mut archive = ZipArchive::new(reader)
+    .map_err(|e| Error::from_reason(format!("Failed to read spooled entry: {}", e)))?;
+  let size = archive
+    .by_index_raw(0)
+    .map_err(|e| Error::from_reason(format!("Failed to read spooled entry: {}", e)))?
+    .size();
+  // Merging copies the spooled local header as is, which `raw_copy_file` would
+  // rewrite with a data descriptor flag but no descriptor on streams
+  zip
+    .merge_archive(archive)
+    .map_err(|e| Error::from_reason(format!("Failed to write zip entry: {}", e)))?;
+  Ok(size)
+}
+
+/// Extracts file entries on a pool of `threads` workers, each reading through
+/// its own `ZipArchive` handle on `source`.
+pub fn extract_files(
+  source: &ArchiveInput,
+  files: &[&ExtractEntry],
+  threads: usize,
+  password: Option<&str>,
+  cancel: &CancelToken,
+  progress: &Mutex<ProgressReporter>,
+  created: &Mutex<CreatedPaths>,
+) -> Result<()> {
+  let next = AtomicUsize::new(0);
+  let failed = AtomicBool::new(false);
+  let first_error: Mutex<Option<Error>> = Mutex::new(None);
+
+  std::thread::scope(|scope| {
+    for _ in 0..threads.min(files.len()) {
+      scope.spawn(|| {
+        let result = (|| {
+          let mut archive = source.open()?;
+          let mut buffer = vec![0; 65536]; // Reusable 64KB read buffer
+
+          // Entries are handed out one at a time, so large files don't stall a worker's share
+          while !failed.load(Ordering::Relaxed) {
+            let Some(entry) = files.get(next.fetch_add(1, Ordering::Relaxed)) else {
+              break;
+            };
+            cancel.check()?;
+            extract_file(
+              &mut archive,
+              entry,
+              password,
+              &mut buffer,
+              cancel,
+              progress,
+              created,
+            )?;
+          }
+          Ok(())
+        })();
+
+        if let Err(e) = result {
+          failed.store(true, Ordering::Relaxed);
+          first_error.lock().unwrap().get_or_insert(e);
+        }
+      });
+    }
+  });
+
+  match first_error.into_inner().unwrap() {
+    Some(e) => Err(e),
+    None => Ok(()),
+  }
+}
@@ -0,0 +1,99 @@
+use napi::Status;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use std::time::{Duration, Instant};
+
+/// Minimum delay between two `onProgress` calls
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Progress snapshot passed to the `onProgress` callback.
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct Progress {
+  /// Number of entries processed so far
+  pub entries_processed: u32,
+  /// Total number of entries, when known upfront
+  pub entries_total: Option<u32>,
+  /// Number of uncompressed bytes read (zip) or written (unzip) so far
+  pub bytes_processed: i64,
+  /// Total number of uncompressed bytes, when known upfront
+  pub bytes_total: Option<i64>,
+  /// Name of the entry currently being processed
+  pub current_entry: String,
+}
+
+/// JS callback `(progress: Progress) => void` invoked from the worker thread.
+pub type ProgressCallback = ThreadsafeFunction<Progress, (), Progress, Status, false>;
+
+/// Tracks task progress and forwards throttled snapshots to the JS callback.
+pub struct ProgressReporter<'a> {
+  callback: Option<&'a ProgressCallback>,
+  state: Progress,
+  last_emit: Option<Instant>,
+}
+
+impl<'a> ProgressReporter<'a> {
+  pub fn new(
+    callback: Option<&'a ProgressCallback>,
+    entries_total: Option<u32>,
+    bytes_total: Option<i64>,
+  ) -> Self {
+    ProgressReporter {
+      callback,
+      state: Progress {
+        entries_total,
+        bytes_total,
+        ..Default::default()
+      },
+      last_emit: None,
+    }
+  }
+
+  pub fn start_entry(&mut self, name: &str) {
+    if self.callback.is_none() {
+      return;
+    }
+    self.state.current_entry.clear();
+    self.state.current_entry.push_str(name);
+    self.emit(false);
+  }
+
+  pub fn add_bytes(&mut self, count: usize) {
+    if self.callback.is_none() {
+      return;
+    }
+    self.state.bytes_processed += count as i64;
+    self.emit(false);
+  }
+
+  pub fn finish_entry(&mut self) {
+    if self.callback.is_none() {
+      return;
+    }
+    self.state.entries_processed += 1;
+    self.emit(false);
+  }
+
+  /// Always emits the final snapshot, bypassing the throttle.
+  pub fn finish(&mut self) {
+    self.emit(true);
+  }
+
+  fn emit(&mut self, force: bool) {
+    let Some(callback) = self.callback else {
+      return;
+    };
+    let now = Instant::now();
+    #[allow(clippy::collapsible_if)]
+    if !force {
+      if let Some(last) = self.last_emit {
+        if now.duration_since(last) < PROGRESS_INTERVAL {
+          return;
+        }
+      }
+    }
+    self.last_emit = Some(now);
+    // Never block the worker thread on a busy event loop
+    callback.call(self.state.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+  }
+}
@@ -0,0 +1,86 @@
+use napi::bindgen_prelude::{AsyncTask, Buffer};
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use std::io::Read;
+use std::path::PathBuf;
+use zip::read::ZipFile;
+
+use crate::error::{self, ErrorCode};
+use crate::{method, open_archive, open_entry};
+
+/// Upper bound for preallocating the output, as entry sizes come from the archive
+const MAX_PREALLOCATION: u64 = 16 * 1024 * 1024;
+
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct ReadEntryOptions {
+  pub password: Option<String>,
+}
+
+pub struct ReadEntryTask {
+  pub source_path: PathBuf,
+  pub name: String,
+  pub options: ReadEntryOptions,
+}
+
+impl Task for ReadEntryTask {
+  type Output = Vec<u8>;
+  type JsValue = Buffer;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let mut archive = open_archive(&self.source_path)?;
+    let index = archive.index_for_name(&self.name).ok_or_else(|| {
+      ErrorCode::EntryNotFound.error(format!("Entry '{}' not found in zip archive", self.name))
+    })?;
+
+    {
+      let file = archive
+        .by_index_raw(index)
+        .map_err(|e| Error::from_reason(format!("Failed to read zip entry: {}", e)))?;
+      method::check_supported(file.compression(), file.name())?;
+    }
+
+    let password = self.options.password.as_deref();
+    let file = open_entry(&mut archive, index, &self.name, password)?;
+    read_content(file)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output.into())
+  }
+
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    error::reject(&env, err)
+  }
+}
+
+/// Decompresses an opened entry into memory.
+pub(crate) fn read_content<R: Read>(mut file: ZipFile<'_, R>) -> Result<Vec<u8>> {
+  let mut content = Vec::with_capacity(file.size().min(MAX_PREALLOCATION) as usize);
+  file
+    .read_to_end(&mut content)
+    .map_err(|e| Error::from_reason(format!("Failed to decompress file content: {}", e)))?;
+  Ok(content)
+}
+
+/// Read a single entry of a zip file into memory.
+///
+/// Rejects with code `EntryNotFound` when the archive has no entry called `name`.
+///
+/// # Arguments
+/// * `source_path` - Source zip file path
+/// * `name` - Entry name, as returned by `list()`
+/// * `options` - Read options
+///   - `password`: Password of an encrypted entry
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn read_entry(
+  source_path: String,
+  name: String,
+  options: Option<ReadEntryOptions>,
+) -> AsyncTask<ReadEntryTask> {
+  AsyncTask::new(ReadEntryTask {
+    source_path: PathBuf::from(source_path),
+    name,
+    options: options.unwrap_or_default(),
+  })
+}
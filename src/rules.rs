@@ -0,0 +1,321 @@
+use glob::Pattern;
+use indexmap::IndexMap;
+use napi::bindgen_prelude::Either;
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zip::DateTime;
+use zip::write::FullFileOptions;
+
+use crate::encryption::Encryption;
+use crate::filter::compile_pattern;
+use crate::method::Method;
+use crate::time::{dos_time, unix_millis};
+use crate::{SourceEntry, ZipOptions, entry_mode, entry_options, with_mtime};
+
+/// Extensions of formats that are already compressed, stored as is by default
+#[rustfmt::skip]
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+  // Images
+  "png", "jpg", "jpeg", "gif", "webp", "avif", "heic", "heif", "jxl",
+  // Audio and video
+  "mp3", "m4a", "aac", "ogg", "opus", "flac", "mp4", "m4v", "mov", "mkv", "webm", "avi",
+  // Archives and compressed streams
+  "zip", "gz", "tgz", "bz2", "tbz2", "xz", "txz", "zst", "lz4", "br", "7z", "rar",
+  // Zip based containers
+  "jar", "war", "apk", "ipa", "whl", "nupkg", "docx", "xlsx", "pptx", "odt", "ods", "odp", "epub",
+  // Fonts
+  "woff", "woff2",
+];
+
+/// Bytes sampled by `detectIncompressible`
+const SAMPLE_SIZE: u64 = 65536;
+
+/// Samples smaller than this are too small to judge and always compressed
+const MIN_SAMPLE_SIZE: usize = 4096;
+
+/// Shannon entropy (bits per byte) above which a sample is treated as incompressible
+const ENTROPY_THRESHOLD: f64 = 7.5;
+
+/// Compression settings for entries matching an `overrides` glob.
+#[napi(object)]
+#[derive(Clone, Copy)]
+pub struct EntryOverride {
+  pub method: Option<Method>,
+  pub level: Option<i32>,
+}
+
+/// Glob to method (`"store"`) or method and level (`{ level: 9 }`), in JS key order.
+pub type Overrides = IndexMap<String, Either<Method, EntryOverride>>;
+
+/// Picks the compression options of each entry.
+pub(crate) struct EntryRules {
+  base_options: FullFileOptions<'static>,
+  method: Method,
+  level: Option<i32>,
+  overrides: Vec<(Pattern, EntryOverride)>,
+  match_options: glob::MatchOptions,
+  store_extensions: HashSet<String>,
+  detect_incompressible: bool,
+  encryption: Option<(Encryption, String)>,
+  /// Modification time of every entry in deterministic mode, in seconds since the Unix epoch
+  deterministic: Option<i64>,
+  /// Directory every entry name goes in, empty or ending with `/`
+  prefix: String,
+}
+
+impl EntryRules {
+  /// Validates methods, levels and override globs of `options`.
+  pub fn new(options: &ZipOptions) -> Result<Self> {
+    let method = options.method.unwrap_or(Method::Deflate);
+    method.validate(options.level)?;
+    let base_options = method.file_options(options.level);
+
+    let mut overrides = Vec::new();
+    for (glob, value) in options.overrides.iter().flatten() {
+      let pattern = compile_pattern("override", glob)?;
+      let entry_override = match value {
+        Either::A(method) => EntryOverride {
+          method: Some(*method),
+          level: None,
+        },
+        Either::B(entry_override) => *entry_override,
+      };
+      entry_override
+        .method
+        .unwrap_or(method)
+        .validate(entry_override.level)?;
+      overrides.push((pattern, entry_override));
+    }
+
+    let store_extensions = if !options.auto_store.unwrap_or(true) {
+      HashSet::new()
+    } else if let Some(extensions) = &options.store_extensions {
+      extensions
+        .iter()
+        .map(|e| e.trim_start_matches('.').to_ascii_lowercase())
+        .collect()
+    } else {
+      COMPRESSED_EXTENSIONS
+        .iter()
+        .map(|e| e.to_string())
+        .collect()
+    };
+
+    let encryption = match (&options.password, options.encryption) {
+      (Some(password), encryption) => {
+        let encryption = encryption.unwrap_or(Encryption::Aes256);
+        encryption.validate()?;
+        Some((encryption, password.clone()))
+      }
+      (None, Some(_)) => return Err(Error::from_reason("Encryption requires a password")),
+      (None, None) => None,
+    };
+
+    let deterministic = if options.deterministic.unwrap_or(false) {
+      if encryption.is_some() {
+        return Err(Error::from_reason(
+          "Deterministic archives can't be encrypted, encryption uses random salts",
+        ));
+      }
+      Some(deterministic_mtime()?)
+    } else {
+      None
+    };
+
+    let prefix = match &options.prefix {
+      Some(prefix) => archive_prefix(prefix)?,
+      None => String::new(),
+    };
+
+    Ok(EntryRules {
+      base_options,
+      method,
+      level: options.level,
+      overrides,
+      match_options: options.match_options.unwrap_or_default().into(),
+      store_extensions,
+      detect_incompressible: options.detect_incompressible.unwrap_or(false),
+      encryption,
+      deterministic,
+      prefix,
+    })
+  }
+
+  /// Options for a file entry, encrypted when a password is set.
+  pub fn file_options(&self, entry: &SourceEntry) -> FullFileOptions<'_> {
+    // Zip64 fields only where the sizes need them, as merging spools drops them
+    let options = self.compression_options(entry).large_file(entry.is_large());
+    match &self.encryption {
+      Some((encryption, password)) => encryption.apply(options, password),
+      None => options,
+    }
+  }
+
+  /// The first matching override wins, then auto store.
+  fn compression_options(&self, entry: &SourceEntry) -> FullFileOptions<'static> {
+    let options = self.entry_options(entry);
+
+    if let Some((_, entry_override)) = self
+      .overrides
+      .iter()
+      .find(|(p, _)| p.matches_with(self.source_name(entry), self.match_options))
+    {
+      let method = entry_override.method.unwrap_or(self.method);
+      // Keep the global level unless the override switches to another method
+      let level = match entry_override.level {
+        Some(level) => Some(level),
+        None if method == self.method => self.level,
+        None => None,
+      };
+      return method.apply(options, level);
+    }
+
+    if self.is_precompressed(entry) {
+      return Method::Store.apply(options, None);
+    }
+    options
+  }
+
+  /// Whether entries can be written without seeking back over them.
+  ///
+  /// AES patches its extra field after the data, and ZipCrypto derives its
+  /// password check from the CRC32, so streams spool encrypted entries.
+  pub fn streamable(&self) -> bool {
+    self.encryption.is_none()
+  }
+
+  /// Whether entries get a fixed mtime, which can't tell changed sources apart.
+  pub fn is_deterministic(&self) -> bool {
+    self.deterministic.is_some()
+  }
+
+  /// Directory entries of `prefix` and its parents, to write before the other entries.
+  pub fn prefix_directories(&self) -> Vec<SourceEntry> {
+    self
+      .prefix
+      .match_indices('/')
+      .map(|(end, _)| SourceEntry {
+        content: Some(Arc::from([])),
+        ..SourceEntry::from_path(PathBuf::new(), self.prefix[..end].to_string(), false)
+      })
+      .collect()
+  }
+
+  /// Moves `entry` under `prefix`.
+  pub fn with_prefix(&self, mut entry: SourceEntry) -> SourceEntry {
+    if !self.prefix.is_empty() {
+      entry.name.insert_str(0, &self.prefix);
+    }
+    entry
+  }
+
+  /// Name of `entry` without `prefix`, which globs match.
+  fn source_name<'a>(&self, entry: &'a SourceEntry) -> &'a str {
+    entry
+      .name
+      .strip_prefix(self.prefix.as_str())
+      .unwrap_or(&entry.name)
+  }
+
+  pub fn directory_options(&self, entry: &SourceEntry) -> FullFileOptions<'static> {
+    self.entry_options(entry)
+  }
+
+  /// Permissions and modification time of `entry`, normalized in deterministic mode.
+  fn entry_options(&self, entry: &SourceEntry) -> FullFileOptions<'static> {
+    let Some(mtime) = self.deterministic else {
+      return entry_options(self.base_options.clone(), entry);
+    };
+    let mode = if entry.link_target.is_some() {
+      0o777
+    } else if !entry.is_file || entry_mode(entry).is_some_and(|mode| mode & 0o111 != 0) {
+      0o755
+    } else {
+      0o644
+    };
+    with_mtime(self.base_options.clone(), mtime).unix_permissions(mode)
+  }
+
+  fn is_precompressed(&self, entry: &SourceEntry) -> bool {
+    let extension = Path::new(&entry.name)
+      .extension()
+      .and_then(|e| e.to_str())
+      .map(|e| e.to_ascii_lowercase());
+    if extension.is_some_and(|e| self.store_extensions.contains(&e)) {
+      return true;
+    }
+    self.detect_incompressible && is_incompressible(entry)
+  }
+}
+
+/// `SOURCE_DATE_EPOCH` when set, as reproducible builds expect, else the earliest zip timestamp.
+fn deterministic_mtime() -> Result<i64> {
+  let Ok(epoch) = std::env::var("SOURCE_DATE_EPOCH") else {
+    return Ok(unix_millis(DateTime::default()) / 1000);
+  };
+  epoch
+    .trim()
+    .parse::<i64>()
+    .ok()
+    .filter(|&seconds| dos_time(seconds.saturating_mul(1000)).is_some())
+    .ok_or_else(|| {
+      Error::from_reason(format!(
+        "SOURCE_DATE_EPOCH '{}' is not a timestamp in the 1980-2107 range of zip timestamps",
+        epoch
+      ))
+    })
+}
+
+/// Samples the first block of `entry` and checks whether it looks like random data.
+fn is_incompressible(entry: &SourceEntry) -> bool {
+  let Ok(file) = entry.open() else {
+    return false;
+  };
+  let mut sample = Vec::with_capacity(SAMPLE_SIZE as usize);
+  if file.take(SAMPLE_SIZE).read_to_end(&mut sample).is_err() || sample.len() < MIN_SAMPLE_SIZE {
+    return false;
+  }
+  entropy(&sample) > ENTROPY_THRESHOLD
+}
+
+/// Shannon entropy in bits per byte.
+fn entropy(data: &[u8]) -> f64 {
+  let mut counts = [0usize; 256];
+  for &byte in data {
+    counts[byte as usize] += 1;
+  }
+  let len = data.len() as f64;
+  counts
+    .iter()
+    .filter(|&&count| count > 0)
+    .map(|&count| {
+      let p = count as f64 / len;
+      -p * p.log2()
+    })
+    .sum()
+}
+
+/// Normalizes the `prefix` option into a relative directory ending with `/`.
+pub(crate) fn archive_prefix(prefix: &str) -> Result<String> {
+  // Normalize path separator to / on Windows
+  let prefix = prefix.replace('\\', "/");
+  let mut normalized = String::new();
+  for part in prefix
+    .split('/')
+    .filter(|part| !part.is_empty() && *part != ".")
+  {
+    if part == ".." || part.contains(':') {
+      return Err(Error::from_reason(format!(
+        "Invalid prefix '{}', it must be a relative path without '..'",
+        prefix
+      )));
+    }
+    normalized.push_str(part);
+    normalized.push('/');
+  }
+  Ok(normalized)
+}
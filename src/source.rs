@@ -0,0 +1,291 @@
+use napi::bindgen_prelude::Either;
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::{Path, PathBuf};
+
+use crate::filter::{EntryFilter, compile_pattern};
+use crate::rules::archive_prefix;
+use crate::{SourceEntry, Symlinks, ZipOptions, symlink_entry, walk_entries};
+
+/// A file, directory or glob for `zip()`, and where it goes in the archive.
+#[napi(object)]
+pub struct ZipSource {
+  /// File, directory or glob pattern
+  pub src: String,
+  /// Archive name of a file, or the directory it goes in when ending with `/`.
+  /// The directory the contents of directories and glob matches go in (default: the archive root)
+  pub dest: Option<String>,
+}
+
+/// A `ZipSource` with its archive names worked out.
+pub(crate) struct Source {
+  src: String,
+  /// Archive directory of the entries, empty or ending with `/`
+  prefix: String,
+  /// Archive name when `src` is a file
+  rename: Option<String>,
+  is_glob: bool,
+}
+
+/// What `zip()` and `zipStream()` compress.
+pub(crate) enum Sources {
+  /// A single directory, whose contents go at the archive root
+  Directory(PathBuf),
+  List(Vec<Source>),
+}
+
+impl Sources {
+  /// Validates the globs among `sources`.
+  pub fn new(sources: Either<String, Vec<ZipSource>>) -> Result<Self> {
+    let sources = match sources {
+      Either::A(source_dir) => return Ok(Sources::Directory(PathBuf::from(source_dir))),
+      Either::B(sources) => sources,
+    };
+    let sources = sources
+      .into_iter()
+      .map(|source| {
+        let is_glob = is_glob(&source.src);
+        if is_glob {
+          compile_pattern("source", &source.src)?;
+        }
+        let (prefix, rename) = archive_dest(source.dest.as_deref().unwrap_or_default())?;
+        Ok(Source {
+          src: source.src,
+          rename,
+          prefix,
+          is_glob,
+        })
+      })
+      .collect::<Result<_>>()?;
+    Ok(Sources::List(sources))
+  }
+
+  /// Entries of every source, in order.
+  ///
+  /// Directories several sources map to are stored once, other entries mapping
+  /// to the same name fail instead of being stored twice.
+  pub fn entries<'a>(
+    &'a self,
+    filter: &'a EntryFilter,
+    options: &'a ZipOptions,
+  ) -> Box<dyn Iterator<Item = Result<SourceEntry>> + 'a> {
+    let sources = match self {
+      Sources::Directory(source_dir) => {
+        return Box::new(walk_entries(source_dir, "", filter, options));
+      }
+      Sources::List(sources) => sources,
+    };
+
+    // Source and kind of every name so far
+    let mut seen: HashMap<String, (&str, bool)> = HashMap::new();
+    Box::new(
+      sources
+        .iter()
+        .flat_map(move |source| {
+          source_entries(source, filter, options).map(move |entry| (source, entry))
+        })
+        .filter_map(move |(source, entry)| {
+          let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e)),
+          };
+          let is_dir = !entry.is_file && entry.link_target.is_none();
+          match seen.entry(entry.name.clone()) {
+            Entry::Vacant(vacant) => {
+              vacant.insert((&source.src, is_dir));
+              Some(Ok(entry))
+            }
+            Entry::Occupied(occupied) if is_dir && occupied.get().1 => None,
+            Entry::Occupied(occupied) => Some(Err(Error::from_reason(format!(
+              "Sources '{}' and '{}' both map to '{}'",
+              occupied.get().0,
+              source.src,
+              entry.name
+            )))),
+          }
+        }),
+    )
+  }
+}
+
+/// Entries of a single source.
+fn source_entries<'a>(
+  source: &'a Source,
+  filter: &'a EntryFilter,
+  options: &'a ZipOptions,
+) -> Box<dyn Iterator<Item = Result<SourceEntry>> + 'a> {
+  let symlinks = options.symlinks.unwrap_or(Symlinks::Follow);
+  if source.is_glob {
+    return glob_entries(source, filter, options);
+  }
+
+  let path = Path::new(&source.src);
+  let metadata = match std::fs::symlink_metadata(path) {
+    Ok(metadata) => metadata,
+    Err(e) => {
+      return Box::new(std::iter::once(Err(Error::from_reason(format!(
+        "Failed to read source '{}': {}",
+        source.src, e
+      )))));
+    }
+  };
+  let is_dir = match metadata.is_symlink() && symlinks == Symlinks::Follow {
+    true => path.is_dir(),
+    false => metadata.is_dir(),
+  };
+  if is_dir {
+    return Box::new(walk_entries(path, &source.prefix, filter, options));
+  }
+
+  let name = match (
+    &source.rename,
+    path.file_name().and_then(|name| name.to_str()),
+  ) {
+    (Some(rename), _) => rename.clone(),
+    (None, Some(file_name)) => format!("{}{}", source.prefix, file_name),
+    (None, None) => {
+      return Box::new(std::iter::once(Err(Error::from_reason(format!(
+        "Source '{}' has no file name",
+        source.src
+      )))));
+    }
+  };
+  Box::new(path_entry(path, name, filter, symlinks).into_iter())
+}
+
+/// Entries matching a glob source, named after their path from the glob's base directory.
+///
+/// Matched directories are walked, and their contents other matches repeat are skipped.
+fn glob_entries<'a>(
+  source: &'a Source,
+  filter: &'a EntryFilter,
+  options: &'a ZipOptions,
+) -> Box<dyn Iterator<Item = Result<SourceEntry>> + 'a> {
+  let symlinks = options.symlinks.unwrap_or(Symlinks::Follow);
+  let match_options = options.match_options.unwrap_or_default().into();
+  let paths = match glob::glob_with(&source.src, match_options) {
+    Ok(paths) => paths,
+    // Already checked by `Sources::new`
+    Err(e) => {
+      return Box::new(std::iter::once(Err(Error::from_reason(format!(
+        "Invalid source pattern '{}': {}",
+        source.src, e
+      )))));
+    }
+  };
+
+  let base = glob_base(&source.src);
+  // Directories matched and walked so far, whose contents other matches would repeat
+  let mut walked: Vec<PathBuf> = Vec::new();
+  Box::new(
+    // Unreadable paths are skipped, like in directory walks
+    paths.filter_map(|path| path.ok()).flat_map(
+      move |path| -> Box<dyn Iterator<Item = Result<SourceEntry>> + 'a> {
+        if walked.iter().any(|dir| path.starts_with(dir)) {
+          return Box::new(std::iter::empty());
+        }
+        let name = match path.strip_prefix(&base).ok().and_then(|name| name.to_str()) {
+          Some("") => return Box::new(std::iter::empty()),
+          Some(name) => name,
+          None => {
+            return Box::new(std::iter::once(Err(Error::from_reason(
+              "Path contains invalid characters",
+            ))));
+          }
+        };
+
+        // Normalize path separator to / on Windows
+        #[cfg(windows)]
+        let name = format!("{}{}", source.prefix, name.replace("\\", "/"));
+        #[cfg(not(windows))]
+        let name = format!("{}{}", source.prefix, name);
+
+        // Directories are walked like directory sources, even when not selected themselves
+        let is_dir = match symlinks {
+          Symlinks::Follow => path.is_dir(),
+          _ => std::fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir()),
+        };
+        if !is_dir || filter.is_pruned(&name, true) {
+          return Box::new(path_entry(&path, name, filter, symlinks).into_iter());
+        }
+        let contents = walk_entries(&path, &format!("{}/", name), filter, options);
+        walked.push(path.clone());
+        Box::new(
+          path_entry(&path, name, filter, symlinks)
+            .into_iter()
+            .chain(contents),
+        )
+      },
+    ),
+  )
+}
+
+/// A file, directory or symlink given by its path, without walking into directories.
+///
+/// Returns `None` for filtered out entries and broken links that are followed.
+fn path_entry(
+  path: &Path,
+  name: String,
+  filter: &EntryFilter,
+  symlinks: Symlinks,
+) -> Option<Result<SourceEntry>> {
+  let metadata = match std::fs::symlink_metadata(path) {
+    Ok(metadata) => metadata,
+    Err(e) => {
+      return Some(Err(Error::from_reason(format!(
+        "Failed to read source '{}': {}",
+        path.display(),
+        e
+      ))));
+    }
+  };
+  let is_link = metadata.is_symlink();
+  let metadata = match (is_link, symlinks) {
+    (true, Symlinks::Skip) => return None,
+    (true, Symlinks::Follow) => std::fs::metadata(path).ok()?,
+    _ => metadata,
+  };
+
+  let is_dir = metadata.is_dir();
+  if !filter.is_selected(&name) || filter.is_pruned(&name, is_dir) {
+    return None;
+  }
+  if is_link && symlinks == Symlinks::Preserve {
+    return Some(symlink_entry(path, name));
+  }
+  Some(Ok(SourceEntry::from_path(
+    path.to_path_buf(),
+    name,
+    metadata.is_file(),
+  )))
+}
+
+/// Normalizes `dest` into the directory of directories and globs, empty or ending
+/// with `/`, and the archive name of a file, unless `dest` ends with `/`.
+fn archive_dest(dest: &str) -> Result<(String, Option<String>)> {
+  let prefix = archive_prefix(dest).map_err(|_| {
+    Error::from_reason(format!(
+      "Invalid dest '{}', it must be a relative path without '..'",
+      dest
+    ))
+  })?;
+  let rename = match dest.ends_with(['/', '\\']) || prefix.is_empty() {
+    true => None,
+    false => Some(prefix.trim_end_matches('/').to_string()),
+  };
+  Ok((prefix, rename))
+}
+
+fn is_glob(src: &str) -> bool {
+  src.contains(['*', '?', '['])
+}
+
+/// Leading directories of a glob without wildcards, which match names are relative to.
+fn glob_base(pattern: &str) -> PathBuf {
+  Path::new(pattern)
+    .components()
+    .take_while(|component| !is_glob(&component.as_os_str().to_string_lossy()))
+    .collect()
+}
@@ -0,0 +1,134 @@
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+/// How `zip()` handles symlinks found in the source directory.
+///
+/// `preserve` stores the link itself with its target, `follow` stores what it
+/// points to, and `skip` leaves it out.
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symlinks {
+  Preserve,
+  Follow,
+  Skip,
+}
+
+/// File type bits of a unix mode
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Whether a Unix `mode` from the central directory is a symlink's.
+pub(crate) fn is_symlink_mode(mode: u32) -> bool {
+  mode & S_IFMT == S_IFLNK
+}
+
+/// Links followed at most while resolving a target, like `MAXSYMLINKS` on Linux
+const MAX_LINK_HOPS: u32 = 40;
+
+/// Resolves `target` of the symlink entry at the enclosed path `link`, following
+/// the links already restored under `output_dir`.
+///
+/// Returns the target relative to the output directory, or `None` when it is
+/// absolute or climbs out of it, the Zip Slip check for link targets.
+fn enclosed_target(output_dir: &Path, link: &Path, target: &str) -> Option<PathBuf> {
+  if target.is_empty() {
+    return None;
+  }
+  let mut resolution = Resolution {
+    output_dir,
+    resolved: PathBuf::new(),
+    pending: false,
+    hops: 0,
+  };
+  resolution.walk(link.parent().unwrap_or(Path::new("")))?;
+  resolution.walk(Path::new(target))?;
+  Some(resolution.resolved)
+}
+
+/// A path under the output directory, resolved component by component.
+struct Resolution<'a> {
+  output_dir: &'a Path,
+  /// Resolved path so far, relative to `output_dir` and without links
+  resolved: PathBuf,
+  /// Whether a component was missing or not a directory, which a later entry could
+  /// still turn into a link, like the files `unzipStream` holds link targets in
+  pending: bool,
+  hops: u32,
+}
+
+impl Resolution<'_> {
+  fn walk(&mut self, path: &Path) -> Option<()> {
+    for component in path.components() {
+      match component {
+        Component::Normal(part) => self.push(part)?,
+        Component::CurDir => {}
+        // Going up from a missing directory could climb out of a link restored later
+        Component::ParentDir if self.pending => return None,
+        Component::ParentDir => {
+          if !self.resolved.pop() {
+            return None;
+          }
+        }
+        Component::RootDir | Component::Prefix(_) => return None,
+      }
+    }
+    Some(())
+  }
+
+  fn push(&mut self, part: &OsStr) -> Option<()> {
+    self.resolved.push(part);
+    if self.pending {
+      return Some(());
+    }
+    let path = self.output_dir.join(&self.resolved);
+    match std::fs::symlink_metadata(&path) {
+      Ok(metadata) if metadata.is_symlink() => {
+        self.hops += 1;
+        if self.hops > MAX_LINK_HOPS {
+          return None;
+        }
+        let target = std::fs::read_link(&path).ok()?;
+        self.resolved.pop();
+        self.walk(&target)
+      }
+      Ok(metadata) if metadata.is_dir() => Some(()),
+      _ => {
+        self.pending = true;
+        Some(())
+      }
+    }
+  }
+}
+
+/// Creates a symlink to `target` at `outpath` when the target stays inside `output_dir`,
+/// also through the links already restored there.
+///
+/// Returns false for links pointing elsewhere, which are skipped like Zip Slip
+/// entries. Without Unix symlinks the target is written as the file content
+/// instead, like the zip crate does.
+pub(crate) fn restore_symlink(output_dir: &Path, outpath: &Path, target: &str) -> Result<bool> {
+  let link = outpath.strip_prefix(output_dir).unwrap_or(outpath);
+  if enclosed_target(output_dir, link, target).is_none() {
+    return Ok(false);
+  }
+  create_symlink(target, outpath)
+    .map_err(|e| Error::from_reason(format!("Failed to create symlink: {}", e)))?;
+  Ok(true)
+}
+
+/// Replaces the file at `outpath` with a symlink to `target`.
+fn create_symlink(target: &str, outpath: &Path) -> std::io::Result<()> {
+  #[cfg(unix)]
+  {
+    if std::fs::symlink_metadata(outpath).is_ok_and(|metadata| !metadata.is_dir()) {
+      std::fs::remove_file(outpath)?;
+    }
+    std::os::unix::fs::symlink(target, outpath)
+  }
+  #[cfg(not(unix))]
+  {
+    std::fs::write(outpath, target)
+  }
+}
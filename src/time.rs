@@ -0,0 +1,129 @@
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zip::DateTime;
+use zip::extra_fields::ExtraField;
+use zip::read::ZipFile;
+
+/// Header ID of the Info-ZIP extended timestamp (UT) extra field
+pub const EXTENDED_TIMESTAMP_ID: u16 = 0x5455;
+
+/// Flag of an extended timestamp field holding the modification time
+const EXTENDED_TIMESTAMP_MTIME: u8 = 1;
+
+/// Milliseconds since the Unix epoch of an MS-DOS timestamp, read as UTC.
+pub fn unix_millis(time: DateTime) -> i64 {
+  let days = days_from_civil(time.year() as i64, time.month() as i64, time.day() as i64);
+  let seconds =
+    days * 86400 + time.hour() as i64 * 3600 + time.minute() as i64 * 60 + time.second() as i64;
+  seconds * 1000
+}
+
+/// MS-DOS timestamp of `millis` since the Unix epoch, in UTC.
+///
+/// Returns `None` outside of the 1980-2107 range zip timestamps can hold.
+pub fn dos_time(millis: i64) -> Option<DateTime> {
+  let seconds = millis.div_euclid(1000);
+  let (days, seconds) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+  let (year, month, day) = civil_from_days(days);
+  DateTime::from_date_and_time(
+    u16::try_from(year).ok()?,
+    month as u8,
+    day as u8,
+    (seconds / 3600) as u8,
+    (seconds % 3600 / 60) as u8,
+    (seconds % 60) as u8,
+  )
+  .ok()
+}
+
+/// Data of an extended timestamp (UT) extra field holding only the modification time.
+///
+/// Returns `None` outside of the signed 32-bit seconds the field can hold.
+pub fn extended_timestamp(seconds: i64) -> Option<[u8; 5]> {
+  let seconds = i32::try_from(seconds).ok()?;
+  let mut data = [EXTENDED_TIMESTAMP_MTIME, 0, 0, 0, 0];
+  data[1..].copy_from_slice(&seconds.to_le_bytes());
+  Some(data)
+}
+
+/// Modification time in an extended timestamp (UT) extra field, when it holds one.
+pub fn parse_extended_timestamp(data: &[u8]) -> Option<i64> {
+  if data.len() < 5 || data[0] & EXTENDED_TIMESTAMP_MTIME == 0 {
+    return None;
+  }
+  Some(i32::from_le_bytes(data[1..5].try_into().unwrap()) as i64)
+}
+
+/// Modification time of an archive entry in seconds since the Unix epoch.
+///
+/// Prefers the extended timestamp field, which is UTC with second precision,
+/// over the DOS timestamp, which is read as UTC.
+pub fn archive_mtime<R: io::Read>(file: &ZipFile<'_, R>) -> Option<i64> {
+  let extended = file.extra_data_fields().find_map(|field| match field {
+    ExtraField::ExtendedTimestamp(timestamp) => timestamp.mod_time(),
+    _ => None,
+  });
+  match extended {
+    Some(seconds) => Some(seconds as i64),
+    None => file.last_modified().map(|time| unix_millis(time) / 1000),
+  }
+}
+
+/// `SystemTime` of `seconds` since the Unix epoch.
+pub fn system_time(seconds: i64) -> SystemTime {
+  match u64::try_from(seconds) {
+    Ok(seconds) => UNIX_EPOCH + Duration::from_secs(seconds),
+    Err(_) => UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()),
+  }
+}
+
+/// Seconds since the Unix epoch of a filesystem timestamp.
+pub fn unix_seconds(time: SystemTime) -> i64 {
+  match time.duration_since(UNIX_EPOCH) {
+    Ok(elapsed) => elapsed.as_secs() as i64,
+    Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+  }
+}
+
+/// Sets the modification time of the directory at `path`.
+pub fn set_dir_mtime(path: &Path, seconds: i64) -> io::Result<()> {
+  #[cfg(windows)]
+  let dir = {
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    std::fs::OpenOptions::new()
+      .access_mode(FILE_WRITE_ATTRIBUTES)
+      .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+      .open(path)?
+  };
+  #[cfg(not(windows))]
+  let dir = std::fs::File::open(path)?;
+  dir.set_modified(system_time(seconds))
+}
+
+// Calendar conversions, see http://howardhinnant.github.io/date_algorithms.html
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = year - i64::from(month <= 2);
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let days = days + 719468;
+  let era = days.div_euclid(146097);
+  let day_of_era = days - era * 146097;
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let mp = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+  (year, month, day)
+}
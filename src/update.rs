@@ -0,0 +1,195 @@
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipWriter;
+use zip::extra_fields::ExtraField;
+
+use crate::cancel::CancelToken;
+use crate::edit::{copy_raw, replace_archive};
+use crate::time::archive_mtime;
+use crate::{CompressTask, SourceEntry, entry_mtime, finish_archive, open_archive};
+
+/// What `zip()` does with an archive already at the output path.
+///
+/// `create` overwrites it. Like Info-ZIP's `-g`, `-u` and `-f`, `append` adds
+/// every source and replaces entries of the same name, `update` only adds new
+/// and changed files, and `freshen` only replaces changed files.
+#[napi(string_enum = "lowercase")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZipMode {
+  Create,
+  Append,
+  Update,
+  Freshen,
+}
+
+/// An entry of the existing archive.
+struct ArchivedEntry {
+  index: usize,
+  size: u64,
+  crc32: u32,
+  mtime: Option<i64>,
+  /// Whether `mtime` comes from an extended timestamp, DOS timestamps are rounded down to even seconds
+  precise: bool,
+}
+
+/// Adds the entries of `task` to the archive at its output path, returning the number of files added or replaced.
+///
+/// New entries are appended in place. When entries are replaced, the others are
+/// copied without recompression into a temp archive that then takes its place.
+pub(crate) fn update_archive(
+  task: &CompressTask,
+  mode: ZipMode,
+  cancel: &CancelToken,
+) -> Result<u32> {
+  let archived = read_entries(&task.output_path)?;
+  let rules = &task.rules;
+
+  // Names were filtered without the prefix, its directories come first
+  let prefix_directories = match mode {
+    ZipMode::Freshen => Vec::new(),
+    _ => rules.prefix_directories(),
+  };
+  let sources = task
+    .sources
+    .entries(&task.filter, &task.options)
+    .map(|entry| entry.map(|entry| rules.with_prefix(entry)));
+
+  let mut entries = Vec::new();
+  let mut replaced = HashSet::new();
+  for entry in prefix_directories.into_iter().map(Ok).chain(sources) {
+    cancel.check()?;
+    let entry = entry?;
+    let is_dir = !entry.is_file && entry.link_target.is_none();
+    let name = match is_dir {
+      true => format!("{}/", entry.name),
+      false => entry.name.clone(),
+    };
+    match archived.get(&name) {
+      None if mode == ZipMode::Freshen => {}
+      None => entries.push(entry),
+      // Directories have no content to replace
+      Some(_) if is_dir => {}
+      Some(archived) => {
+        if mode == ZipMode::Append || is_changed(&entry, archived, rules.is_deterministic())? {
+          replaced.insert(archived.index);
+          entries.push(entry);
+        }
+      }
+    }
+  }
+
+  if entries.is_empty() {
+    return Ok(0);
+  }
+  if replaced.is_empty() {
+    return append_entries(task, entries, cancel);
+  }
+
+  replace_archive(&task.output_path, |archive, mut zip, temp_path| {
+    for index in (0..archive.len()).filter(|index| !replaced.contains(index)) {
+      cancel.check()?;
+      copy_raw(archive, index, &mut zip, None)?;
+    }
+    finish_archive(
+      zip,
+      entries.into_iter().map(Ok),
+      &task.options,
+      &task.rules,
+      Some(temp_path),
+      cancel,
+    )
+  })
+}
+
+/// Name, index, size, CRC32 and mtime of every entry of the archive at `path`.
+fn read_entries(path: &Path) -> Result<HashMap<String, ArchivedEntry>> {
+  let mut archive = open_archive(path)?;
+  (0..archive.len())
+    .map(|index| {
+      let file = archive
+        .by_index_raw(index)
+        .map_err(|e| Error::from_reason(format!("Failed to read zip entry: {}", e)))?;
+      let precise = file
+        .extra_data_fields()
+        .any(|field| matches!(field, ExtraField::ExtendedTimestamp(_)));
+      let entry = ArchivedEntry {
+        index,
+        size: file.size(),
+        crc32: file.crc32(),
+        mtime: archive_mtime(&file),
+        precise,
+      };
+      Ok((file.name().to_string(), entry))
+    })
+    .collect()
+}
+
+/// Whether the source of `entry` differs from its archived copy.
+///
+/// Compares sizes, then modification times, or contents in deterministic
+/// mode where every entry has the same mtime.
+fn is_changed(entry: &SourceEntry, archived: &ArchivedEntry, deterministic: bool) -> Result<bool> {
+  let size = match &entry.link_target {
+    Some(target) => target.len() as u64,
+    None => entry.size()?,
+  };
+  if size != archived.size {
+    return Ok(true);
+  }
+  if deterministic {
+    return Ok(entry_crc32(entry)? != archived.crc32);
+  }
+  let rounding = if archived.precise { 0 } else { 1 };
+  Ok(match (entry_mtime(entry), archived.mtime) {
+    (Some(mtime), Some(archived_mtime)) => mtime > archived_mtime + rounding,
+    _ => true,
+  })
+}
+
+/// CRC32 of the content `entry` would be stored with.
+fn entry_crc32(entry: &SourceEntry) -> Result<u32> {
+  let mut hasher = crc32fast::Hasher::new();
+  if let Some(target) = &entry.link_target {
+    hasher.update(target.as_bytes());
+    return Ok(hasher.finalize());
+  }
+  let mut file = entry.open()?;
+  let mut buffer = vec![0; 65536];
+  loop {
+    let count = file
+      .read(&mut buffer)
+      .map_err(|e| Error::from_reason(format!("File stream read interrupted: {}", e)))?;
+    if count == 0 {
+      return Ok(hasher.finalize());
+    }
+    hasher.update(&buffer[..count]);
+  }
+}
+
+/// Writes `entries` after the existing ones, in place of the old central directory.
+fn append_entries(
+  task: &CompressTask,
+  entries: Vec<SourceEntry>,
+  cancel: &CancelToken,
+) -> Result<u32> {
+  let file = OpenOptions::new()
+    .read(true)
+    .write(true)
+    .open(&task.output_path)
+    .map_err(|e| Error::from_reason(format!("Failed to open zip file: {}", e)))?;
+  let zip = ZipWriter::new_append(file)
+    .map_err(|e| Error::from_reason(format!("Failed to read zip archive: {}", e)))?;
+  let (_, file_count) = finish_archive(
+    zip,
+    entries.into_iter().map(Ok),
+    &task.options,
+    &task.rules,
+    Some(&task.output_path),
+    cancel,
+  )?;
+  Ok(file_count)
+}
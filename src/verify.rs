@@ -0,0 +1,119 @@
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Error, Result, Task};
+use napi_derive::napi;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use zip::ZipArchive;
+
+use crate::{method, open_archive, open_entry};
+
+#[napi(object, object_to_js = false)]
+#[derive(Default)]
+pub struct TestOptions {
+  pub password: Option<String>,
+}
+
+/// An entry that failed the integrity test.
+#[napi(object, object_from_js = false)]
+pub struct EntryError {
+  pub name: String,
+  pub message: String,
+}
+
+/// Result of `test()`.
+#[napi(object, object_from_js = false)]
+pub struct TestReport {
+  /// Number of file entries read through the decompressor
+  pub entries_tested: u32,
+  /// Entries that failed, in archive order
+  pub errors: Vec<EntryError>,
+}
+
+pub struct TestTask {
+  pub source_path: PathBuf,
+  pub options: TestOptions,
+}
+
+impl TestTask {
+  /// Decompresses entry `index` into a sink, checking its CRC32 and size.
+  fn test_entry(&self, archive: &mut ZipArchive<File>, index: usize, name: &str) -> Result<()> {
+    let expected_size = {
+      let file = archive
+        .by_index_raw(index)
+        .map_err(|e| Error::from_reason(format!("Failed to read zip entry: {}", e)))?;
+      method::check_supported(file.compression(), name)?;
+      file.size()
+    };
+
+    let password = self.options.password.as_deref();
+    let file = open_entry(archive, index, name, password)?;
+    // The zip reader checks the CRC32 once the entry is read to the end
+    let size = io::copy(
+      &mut file.take(expected_size.saturating_add(1)),
+      &mut io::sink(),
+    )
+    .map_err(|e| Error::from_reason(format!("Failed to decompress file content: {}", e)))?;
+    if size != expected_size {
+      return Err(Error::from_reason(format!(
+        "Size mismatch: expected {} bytes, got {}",
+        expected_size, size
+      )));
+    }
+    Ok(())
+  }
+}
+
+impl Task for TestTask {
+  type Output = TestReport;
+  type JsValue = TestReport;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let mut archive = open_archive(&self.source_path)?;
+
+    let mut report = TestReport {
+      entries_tested: 0,
+      errors: Vec::new(),
+    };
+    for i in 0..archive.len() {
+      let name = match archive.name_for_index(i) {
+        Some(name) => name.to_string(),
+        None => continue,
+      };
+      if name.ends_with('/') {
+        continue;
+      }
+
+      report.entries_tested += 1;
+      if let Err(e) = self.test_entry(&mut archive, i, &name) {
+        report.errors.push(EntryError {
+          name,
+          message: e.reason,
+        });
+      }
+    }
+    Ok(report)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+/// Test the integrity of a zip file, like `unzip -t`.
+///
+/// Reads every file entry through the decompressor and checks its CRC32 and size
+/// against the central directory. Nothing is written to disk. Failing entries are
+/// collected in the report instead of rejecting the promise.
+///
+/// # Arguments
+/// * `source_path` - Source zip file path
+/// * `options` - Test options
+///   - `password`: Password of encrypted entries
+#[napi(ts_return_type = "Promise<TestReport>")]
+pub fn test(source_path: String, options: Option<TestOptions>) -> AsyncTask<TestTask> {
+  AsyncTask::new(TestTask {
+    source_path: PathBuf::from(source_path),
+    options: options.unwrap_or_default(),
+  })
+}